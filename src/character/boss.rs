@@ -0,0 +1,67 @@
+use super::class::Class;
+use super::{Character, Element};
+use crate::location::Location;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+
+/// A unique enemy that guards a specific location. It shows up every time
+/// the hero visits the location, until it's defeated.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Boss {
+    /// Path pattern of the guarded location, e.g. `/etc` or `~/**/node_modules`.
+    pub location: String,
+    pub level: i32,
+    pub class: Class,
+
+    #[serde(default)]
+    pub loot: Loot,
+}
+
+/// The reward that is guaranteed when beating a boss.
+/// The equipment is of the same level as the boss.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Loot {
+    pub gold: i32,
+    pub sword: bool,
+    pub shield: bool,
+
+    /// Element of the rewarded sword, if any.
+    pub element: Option<Element>,
+}
+
+static BOSSES: OnceCell<Vec<Boss>> = OnceCell::new();
+
+impl Boss {
+    /// Customize the boss definitions based on an input yaml byte array.
+    pub fn load(bytes: &[u8]) {
+        BOSSES.set(from_bytes(bytes)).unwrap();
+    }
+
+    pub fn all() -> &'static [Boss] {
+        BOSSES.get_or_init(default_bosses)
+    }
+
+    /// Return the boss guarding the given location, if any.
+    pub fn at(location: &Location) -> Option<&'static Self> {
+        Self::all()
+            .iter()
+            .find(|boss| location.matches(&boss.location))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.class.name
+    }
+
+    pub fn character(&self) -> Character {
+        Character::new(self.class.clone(), self.level)
+    }
+}
+
+fn default_bosses() -> Vec<Boss> {
+    from_bytes(include_bytes!("bosses.yaml"))
+}
+
+fn from_bytes(bytes: &[u8]) -> Vec<Boss> {
+    serde_yaml::from_slice(bytes).unwrap()
+}
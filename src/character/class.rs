@@ -38,6 +38,78 @@ pub struct Class {
     pub category: Category,
 
     pub inflicts: Option<(super::StatusEffect, u32)>,
+
+    #[serde(default)]
+    pub weak_to: Vec<super::Element>,
+    #[serde(default)]
+    pub resists: Vec<super::Element>,
+
+    #[serde(default)]
+    pub skills: Vec<Skill>,
+
+    #[serde(default)]
+    pub passives: Passives,
+
+    /// Crafting materials that enemies of the class may drop when beaten.
+    #[serde(default)]
+    pub drops: Vec<String>,
+}
+
+/// Permanent bonuses granted to the characters of a class.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Passives {
+    /// Improves the odds of bribing or running away from enemies.
+    pub escape: u32,
+
+    /// Extra chances to get loot after winning a battle.
+    pub loot_rolls: i32,
+
+    /// Percentage of the damage received from attacks that is prevented.
+    pub damage_reduction: i32,
+
+    /// Percentage of the max mp restored on each directory move.
+    pub mp_regen: i32,
+
+    /// Improves the odds of spotting mimics and disarming chest traps.
+    pub trap_detection: u32,
+}
+
+/// An active ability of a player class, used during battle instead of the
+/// regular attack once it's unlocked.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Skill {
+    pub name: String,
+
+    /// The character level at which the skill is unlocked.
+    pub level: i32,
+
+    /// What the skill does when used.
+    pub effect: SkillEffect,
+
+    /// Damage multiplier for attacks, or max hp percentage for heals.
+    #[serde(default)]
+    pub power: i32,
+
+    /// Element of the attack, if it differs from the regular one.
+    #[serde(default)]
+    pub element: Option<super::Element>,
+
+    /// The mp spent, as a multiple of the regular magic attack cost.
+    #[serde(default)]
+    pub mp: i32,
+
+    /// How many turns to wait before using the skill again.
+    #[serde(default)]
+    pub cooldown: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SkillEffect {
+    Attack,
+    Heal,
+    Steal,
 }
 
 /// Determines whether the class is intended for a Player or, if it's for an enemy,
@@ -61,6 +133,17 @@ impl Class {
         self.mp.is_some()
     }
 
+    /// How characters of this class react to attacks of the given element.
+    pub fn affinity(&self, element: super::Element) -> super::Affinity {
+        if self.weak_to.contains(&element) {
+            super::Affinity::Weak
+        } else if self.resists.contains(&element) {
+            super::Affinity::Resistant
+        } else {
+            super::Affinity::Neutral
+        }
+    }
+
     /// Customize the classes definitions based on an input yaml byte array.
     pub fn load(bytes: &[u8]) {
         CLASSES.set(from_bytes(bytes)).unwrap();
@@ -86,6 +169,20 @@ impl Class {
         Self::of(category).choose(&mut rng).unwrap()
     }
 
+    /// Pick a random class of the given category among the ones
+    /// that satisfy the predicate, if any.
+    pub fn random_where<F>(category: Category, predicate: F) -> Option<&'static Self>
+    where
+        F: Fn(&Class) -> bool,
+    {
+        let candidates: Vec<&Class> = Self::of(category)
+            .iter()
+            .filter(|class| predicate(class))
+            .collect();
+        let mut rng = rand::thread_rng();
+        candidates.choose(&mut rng).copied()
+    }
+
     pub fn names(category: Category) -> HashSet<String> {
         Self::of(category)
             .iter()
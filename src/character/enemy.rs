@@ -1,10 +1,15 @@
 use super::{class::Category, class::Class, Character};
 use crate::location;
+use crate::location::biome::Biome;
 use crate::randomizer::{random, Randomizer};
 use rand::prelude::SliceRandom;
 use rand::Rng;
 
-pub fn at(location: &location::Location, player: &Character) -> Character {
+pub fn at(
+    location: &location::Location,
+    features: &location::Features,
+    player: &Character,
+) -> Character {
     let (class, level) = if should_find_shadow(location) {
         let mut class = player.class.clone();
         class.name = String::from("shadow");
@@ -19,13 +24,37 @@ pub fn at(location: &location::Location, player: &Character) -> Character {
     } else {
         let distance = location.distance_from_home();
         let level = level(player.level, distance.len());
-        let category = weighted_choice(distance);
-        (Class::random(category).clone(), level)
+        let biome = location.biome();
+        let allowed = |class: &Class| biome.into_iter().all(|biome| biome.allows(&class.name));
+        let category = weighted_choice(distance, features, biome);
+
+        let class = if features.is_repo && rand::thread_rng().gen_ratio(1, 2) {
+            Class::random_where(category.clone(), |class| {
+                allowed(class) && REPO_DWELLERS.contains(&class.name.as_str())
+            })
+        } else {
+            None
+        };
+        let class = class
+            .or_else(|| Class::random_where(category.clone(), allowed))
+            .unwrap_or_else(|| Class::random(category));
+        (class.clone(), level)
     };
 
     Character::new(class, level)
 }
 
+/// An enemy disguised as a chest, stronger than the ones usually found around.
+pub fn mimic(location: &location::Location, player: &Character) -> Character {
+    let distance = location.distance_from_home();
+    let mut class = Class::random(Category::Rare).clone();
+    class.name = String::from("mimic");
+    Character::new(class, level(player.level, distance.len()) + 1)
+}
+
+/// Enemy classes more likely to be found lurking in git repositories.
+const REPO_DWELLERS: [&str; 5] = ["spider", "snake", "sorcerer", "golem", "basilisk"];
+
 fn level(player_level: i32, distance_from_home: i32) -> i32 {
     let level = std::cmp::max(player_level / 2 + distance_from_home - 1, 1);
     random().enemy_level(level)
@@ -42,30 +71,61 @@ fn should_find_dev(location: &location::Location) -> bool {
 }
 
 /// Choose an enemy randomly, with higher chance to difficult enemies the further from home.
-fn weighted_choice(distance: location::Distance) -> Category {
+fn weighted_choice(
+    distance: location::Distance,
+    features: &location::Features,
+    biome: Option<&Biome>,
+) -> Category {
     // the weights for each group of enemies are different depending on the distance
     // the further from home, the bigger the chance to find difficult enemies
-    let (w_common, w_rare, w_legendary) = match distance {
+    let (mut w_common, mut w_rare, mut w_legendary) = match distance {
         location::Distance::Near(_) => (9, 2, 0),
         location::Distance::Mid(_) => (7, 10, 1),
         location::Distance::Far(_) => (1, 6, 3),
     };
 
+    // biomes can override them regardless of the distance
+    if let Some(biome) = biome {
+        w_common = biome.weight(&Category::Common).unwrap_or(w_common);
+        w_rare = biome.weight(&Category::Rare).unwrap_or(w_rare);
+        w_legendary = biome.weight(&Category::Legendary).unwrap_or(w_legendary);
+    }
+
+    // infested dirs are swarming with small creatures
+    // while hidden ones keep rarer enemies
+    if features.is_infested {
+        w_common *= 2;
+    }
+    if features.is_hidden {
+        w_rare += 4;
+        w_legendary += 1;
+    }
+
     let mut rng = rand::thread_rng();
 
     // assign weights to each group and select one
-    let weights = vec![
+    let mut weights = vec![
         (Category::Common, w_common),
         (Category::Rare, w_rare),
         (Category::Legendary, w_legendary),
     ];
 
+    // skip the groups without enemies living in the biome
+    if let Some(biome) = biome {
+        for (category, weight) in weights.iter_mut() {
+            if !Class::names(category.clone())
+                .iter()
+                .any(|name| biome.allows(name))
+            {
+                *weight = 0;
+            }
+        }
+    }
+
     weights
         .as_slice()
         .choose_weighted(&mut rng, |(_c, weight)| *weight)
-        .unwrap()
-        .0
-        .clone()
+        .map_or(Category::Common, |(category, _)| category.clone())
 }
 
 #[cfg(test)]
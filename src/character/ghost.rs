@@ -0,0 +1,67 @@
+use super::class::Category;
+use super::Character;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Appended to the name of the player that exported a hero,
+/// to name the ghost of that hero.
+const SUFFIX: &str = "'s ghost";
+
+/// How many levels apart a ghost can be from the hero to show up,
+/// so battles against other players are reasonably fair.
+const LEVEL_RANGE: i32 = 5;
+
+/// The outcome of the battles against the ghost of a given player.
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct Record {
+    pub wins: u32,
+    pub losses: u32,
+}
+
+/// Turn a hero exported by the given player into an enemy, keeping its stats
+/// and equipment.
+pub fn enemy(owner: &str, mut hero: Character) -> Character {
+    hero.class.name = format!("{}{}", owner, SUFFIX);
+    hero.class.category = Category::Rare;
+    hero.heal_full();
+    hero.maybe_remove_status_effect();
+    hero
+}
+
+/// Return the player that exported the hero, if the given character is a ghost.
+pub fn owner(character: &Character) -> Option<&str> {
+    character.class.name.strip_suffix(SUFFIX)
+}
+
+pub fn should_appear() -> bool {
+    rand::thread_rng().gen_ratio(1, 10)
+}
+
+/// Whether the ghost of the given hero is a match for the player.
+pub fn is_match(hero: &Character, player: &Character) -> bool {
+    (hero.level - player.level).abs() <= LEVEL_RANGE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::equipment::{Equipment, Sword};
+
+    #[test]
+    fn test_enemy() {
+        let mut hero = Character::player();
+        hero.sword = Some(Sword::new(5));
+        hero.current_hp = 1;
+        let strength = hero.strength;
+
+        let ghost = enemy("alice", hero);
+        assert_eq!("alice's ghost", ghost.name());
+        assert_eq!(Some("alice"), owner(&ghost));
+        assert!(!ghost.is_player());
+        assert_eq!(ghost.max_hp, ghost.current_hp);
+        assert_eq!(strength, ghost.strength);
+        assert!(ghost.sword.is_some());
+
+        assert_eq!(None, owner(&Character::player()));
+    }
+}
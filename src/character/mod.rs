@@ -1,12 +1,14 @@
 use crate::item::equipment;
-use crate::item::equipment::Equipment;
+use crate::item::equipment::{Affix, Equipment, Gear};
 use crate::randomizer::{random, Randomizer};
 use class::Class;
 use serde::{Deserialize, Serialize};
 use std::cmp::{max, min};
 
+pub mod boss;
 pub mod class;
 pub mod enemy;
+pub mod ghost;
 
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(default)]
@@ -14,6 +16,10 @@ pub struct Character {
     pub class: Class,
     pub sword: Option<equipment::Sword>,
     pub shield: Option<equipment::Shield>,
+    pub armor: Option<equipment::Armor>,
+    pub helmet: Option<equipment::Helmet>,
+    pub ring: Option<equipment::Ring>,
+    pub amulet: Option<equipment::Amulet>,
 
     pub level: i32,
     pub xp: i32,
@@ -27,6 +33,9 @@ pub struct Character {
     pub strength: i32,
     pub speed: i32,
     pub status_effect: Option<StatusEffect>,
+
+    /// Turns or moves left before a temporary status effect wears off.
+    status_effect_left: i32,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
@@ -34,6 +43,88 @@ pub struct Character {
 pub enum StatusEffect {
     Burn,
     Poison,
+    Sleep,
+    Stun,
+    Weakness,
+    Regen,
+    Haste,
+}
+
+/// How long a status effect lasts once it's inflicted.
+pub enum Duration {
+    /// Lasts until removed with an item or by visiting home.
+    UntilCured,
+    /// Wears off after the given amount of battle turns.
+    Turns(i32),
+    /// Wears off after the given amount of directory moves.
+    Moves(i32),
+}
+
+impl StatusEffect {
+    pub fn duration(&self) -> Duration {
+        match self {
+            StatusEffect::Burn | StatusEffect::Poison => Duration::UntilCured,
+            StatusEffect::Sleep => Duration::Turns(3),
+            StatusEffect::Stun => Duration::Turns(1),
+            StatusEffect::Weakness => Duration::Moves(5),
+            StatusEffect::Regen | StatusEffect::Haste => Duration::Moves(10),
+        }
+    }
+
+    /// Whether a character under this status loses its battle turns.
+    pub fn skips_turn(&self) -> bool {
+        matches!(self, StatusEffect::Sleep | StatusEffect::Stun)
+    }
+
+    /// Positive effects are buffs, they aren't removed when healing.
+    pub fn is_positive(&self) -> bool {
+        matches!(self, StatusEffect::Regen | StatusEffect::Haste)
+    }
+}
+
+/// Elemental property of an attack, e.g. the one of an enchanted sword.
+/// Classes can be weak to or resist some of them.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Element {
+    Fire,
+    Ice,
+    Poison,
+    Physical,
+    Magic,
+}
+
+impl Element {
+    /// The status effect that attacks of this element can inflict, if any.
+    pub fn status_effect(&self) -> Option<StatusEffect> {
+        match self {
+            Element::Fire => Some(StatusEffect::Burn),
+            Element::Ice => Some(StatusEffect::Stun),
+            Element::Poison => Some(StatusEffect::Poison),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Element {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Element::Fire => "fire",
+            Element::Ice => "ice",
+            Element::Poison => "poison",
+            Element::Physical => "physical",
+            Element::Magic => "magic",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// How the receiver of an attack reacts to the attack element.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Affinity {
+    Neutral,
+    Weak,
+    Resistant,
 }
 
 pub struct Dead;
@@ -69,6 +160,10 @@ impl Character {
             class,
             sword: None,
             shield: None,
+            armor: None,
+            helmet: None,
+            ring: None,
+            amulet: None,
             level: 0,
             xp: 0,
             max_hp,
@@ -78,6 +173,7 @@ impl Character {
             strength,
             speed,
             status_effect: None,
+            status_effect_left: 0,
         };
 
         for _ in 0..level {
@@ -100,11 +196,13 @@ impl Character {
                 // if class change is done at level 1, it works as a game reset
                 // the player stats are regenerated with the new class
                 // if equipment was already set, it is preserved
-                let sword = self.sword.take();
-                let shield = self.shield.take();
-                *self = Self::new(class.clone(), 1);
-                self.sword = sword;
-                self.shield = shield;
+                let previous = std::mem::replace(self, Self::new(class.clone(), 1));
+                self.sword = previous.sword;
+                self.shield = previous.shield;
+                self.armor = previous.armor;
+                self.helmet = previous.helmet;
+                self.ring = previous.ring;
+                self.amulet = previous.amulet;
             } else {
                 self.class = class.clone();
 
@@ -138,18 +236,18 @@ impl Character {
 
         // the current should increase proportionally but not
         // erase previous damage
-        let previous_damage = self.max_hp - self.current_hp;
+        let previous_damage = self.effective_max_hp() - self.current_hp;
         self.max_hp += random().stat_increase(self.class.hp.increase());
-        self.current_hp = self.max_hp - previous_damage;
+        self.current_hp = self.effective_max_hp() - previous_damage;
 
         // same with mp
-        let previous_used_mp = self.max_mp - self.current_mp;
+        let previous_used_mp = self.effective_max_mp() - self.current_mp;
         self.max_mp += self
             .class
             .mp
             .as_ref()
             .map_or(0, |mp| random().stat_increase(mp.increase()));
-        self.current_mp = self.max_mp - previous_used_mp;
+        self.current_mp = self.effective_max_mp() - previous_used_mp;
     }
 
     /// Add to the accumulated experience points, possibly increasing the level.
@@ -181,23 +279,165 @@ impl Character {
         self.current_hp == 0
     }
 
+    /// The max hp, including the bonus of the equipped armor.
+    pub fn effective_max_hp(&self) -> i32 {
+        self.max_hp + self.armor.as_ref().map_or(0, |armor| armor.hp())
+    }
+
+    /// The max mp, including the bonuses of the equipped amulet and affixes.
+    pub fn effective_max_mp(&self) -> i32 {
+        self.max_mp
+            + self.amulet.as_ref().map_or(0, |amulet| amulet.mp())
+            + self.affix_bonus(Affix::Mp)
+    }
+
+    /// The items currently equipped.
+    pub fn equipment(&self) -> Vec<&dyn Equipment> {
+        let slots: [Option<&dyn Equipment>; 6] = [
+            self.sword.as_ref().map(|e| e as _),
+            self.shield.as_ref().map(|e| e as _),
+            self.armor.as_ref().map(|e| e as _),
+            self.helmet.as_ref().map(|e| e as _),
+            self.ring.as_ref().map(|e| e as _),
+            self.amulet.as_ref().map(|e| e as _),
+        ];
+        slots.iter().flatten().copied().collect()
+    }
+
+    /// Put on the given piece of equipment, returning the one
+    /// previously in the same slot, if any.
+    pub fn equip(&mut self, gear: Gear) -> Option<Gear> {
+        let previous = match gear {
+            Gear::Sword(sword) => self.sword.replace(sword).map(Gear::Sword),
+            Gear::Shield(shield) => self.shield.replace(shield).map(Gear::Shield),
+            Gear::Armor(armor) => self.armor.replace(armor).map(Gear::Armor),
+            Gear::Helmet(helmet) => self.helmet.replace(helmet).map(Gear::Helmet),
+            Gear::Ring(ring) => self.ring.replace(ring).map(Gear::Ring),
+            Gear::Amulet(amulet) => self.amulet.replace(amulet).map(Gear::Amulet),
+        };
+        self.clamp_points();
+        previous
+    }
+
+    /// Put on the given piece of equipment only if it's better than the
+    /// current one. Return the piece left out, if any.
+    pub fn equip_if_better(&mut self, gear: Gear) -> Option<Gear> {
+        let left_out = match gear {
+            Gear::Sword(sword) => equipment::keep_best(&mut self.sword, sword).map(Gear::Sword),
+            Gear::Shield(shield) => {
+                equipment::keep_best(&mut self.shield, shield).map(Gear::Shield)
+            }
+            Gear::Armor(armor) => equipment::keep_best(&mut self.armor, armor).map(Gear::Armor),
+            Gear::Helmet(helmet) => {
+                equipment::keep_best(&mut self.helmet, helmet).map(Gear::Helmet)
+            }
+            Gear::Ring(ring) => equipment::keep_best(&mut self.ring, ring).map(Gear::Ring),
+            Gear::Amulet(amulet) => {
+                equipment::keep_best(&mut self.amulet, amulet).map(Gear::Amulet)
+            }
+        };
+        self.clamp_points();
+        left_out
+    }
+
+    /// Take off the equipment of the given slot, if any.
+    pub fn unequip(&mut self, slot: &str) -> Option<Gear> {
+        let gear = match slot {
+            "sword" => self.sword.take().map(Gear::Sword),
+            "shield" => self.shield.take().map(Gear::Shield),
+            "armor" => self.armor.take().map(Gear::Armor),
+            "helmet" => self.helmet.take().map(Gear::Helmet),
+            "ring" => self.ring.take().map(Gear::Ring),
+            "amulet" => self.amulet.take().map(Gear::Amulet),
+            _ => None,
+        };
+        self.clamp_points();
+        gear
+    }
+
+    /// A copy of the equipment worn in the given slot, if any.
+    pub fn gear(&self, slot: &str) -> Option<Gear> {
+        match slot {
+            "sword" => self.sword.clone().map(Gear::Sword),
+            "shield" => self.shield.clone().map(Gear::Shield),
+            "armor" => self.armor.clone().map(Gear::Armor),
+            "helmet" => self.helmet.clone().map(Gear::Helmet),
+            "ring" => self.ring.clone().map(Gear::Ring),
+            "amulet" => self.amulet.clone().map(Gear::Amulet),
+            _ => None,
+        }
+    }
+
+    /// The equipment worn in the given slot, if any.
+    pub fn equipment_mut(&mut self, slot: &str) -> Option<&mut dyn Equipment> {
+        match slot {
+            "sword" => self.sword.as_mut().map(|e| e as _),
+            "shield" => self.shield.as_mut().map(|e| e as _),
+            "armor" => self.armor.as_mut().map(|e| e as _),
+            "helmet" => self.helmet.as_mut().map(|e| e as _),
+            "ring" => self.ring.as_mut().map(|e| e as _),
+            "amulet" => self.amulet.as_mut().map(|e| e as _),
+            _ => None,
+        }
+    }
+
+    /// Don't keep the hp and mp given by equipment that was removed.
+    fn clamp_points(&mut self) {
+        self.current_hp = min(self.current_hp, self.effective_max_hp());
+        self.current_mp = min(self.current_mp, self.effective_max_mp());
+    }
+
+    /// The sum of the given affix bonus across the equipped items.
+    pub fn affix_bonus(&self, affix: Affix) -> i32 {
+        self.equipment()
+            .iter()
+            .flat_map(|equip| equip.affixes())
+            .filter(|(kind, _)| *kind == affix)
+            .map(|(_, value)| value)
+            .sum()
+    }
+
+    /// Restore the hp corresponding to the lifesteal bonus
+    /// for the given inflicted damage. Return the amount actually restored.
+    pub fn steal_life(&mut self, damage: i32) -> i32 {
+        let lifesteal = self.affix_bonus(Affix::Lifesteal);
+        if lifesteal > 0 && damage > 0 {
+            self.heal(max(1, damage * lifesteal / 100))
+        } else {
+            0
+        }
+    }
+
     /// Restore up to the given amount of health points (not exceeding the max_hp).
     /// Return the amount actually restored.
     pub fn heal(&mut self, amount: i32) -> i32 {
         let previous = self.current_hp;
-        self.current_hp = min(self.max_hp, self.current_hp + amount);
+        self.current_hp = min(self.effective_max_hp(), self.current_hp + amount);
         self.current_hp - previous
     }
 
     pub fn restore_mp(&mut self, amount: i32) -> i32 {
         let previous = self.current_mp;
-        self.current_mp = min(self.max_mp, self.current_mp + amount);
+        self.current_mp = min(self.effective_max_mp(), self.current_mp + amount);
         self.current_mp - previous
     }
 
+    /// Restore the amount of mp given by the class passives, if any.
+    pub fn regen_mp(&mut self) -> i32 {
+        let regen = self.class.passives.mp_regen;
+        if regen > 0 {
+            self.restore_mp(max(1, self.effective_max_mp() * regen / 100))
+        } else {
+            0
+        }
+    }
+
     /// Restore all health and magic points to their max
     pub fn heal_full(&mut self) -> (i32, i32) {
-        (self.heal(self.max_hp), self.restore_mp(self.max_mp))
+        (
+            self.heal(self.effective_max_hp()),
+            self.restore_mp(self.effective_max_mp()),
+        )
     }
 
     /// How many experience points are required to move to the next level.
@@ -217,31 +457,113 @@ impl Character {
             (self.physical_attack(), 0)
         };
 
-        (max(1, damage - receiver.deffense()), mp_cost)
+        let affinity = self.attack_affinity(receiver);
+        (receiver.reduce_damage(damage, affinity), mp_cost)
+    }
+
+    /// Damage produced by an attack skill, based on the regular attack
+    /// multiplied by the skill power.
+    pub fn skill_damage(&self, receiver: &Self, skill: &class::Skill) -> i32 {
+        let damage = if self.class.is_magic() {
+            self.magic_attack()
+        } else {
+            self.physical_attack()
+        };
+        let element = skill.element.unwrap_or_else(|| self.attack_element());
+        let affinity = receiver.class.affinity(element);
+        receiver.reduce_damage(damage * skill.power, affinity)
+    }
+
+    /// Apply the defense and elemental affinity of this character to the
+    /// incoming damage.
+    fn reduce_damage(&self, damage: i32, affinity: Affinity) -> i32 {
+        let damage = max(1, damage - self.deffense());
+        let damage = match affinity {
+            Affinity::Weak => damage * 3 / 2,
+            Affinity::Resistant => max(1, damage / 2),
+            Affinity::Neutral => damage,
+        };
+        let reduction = self.class.passives.damage_reduction;
+        max(1, damage * (100 - reduction) / 100)
+    }
+
+    /// The element of this character's next attack: magic if it can cast spells,
+    /// otherwise that of its sword, or just physical.
+    pub fn attack_element(&self) -> Element {
+        if self.can_magic_attack() {
+            Element::Magic
+        } else {
+            self.sword
+                .as_ref()
+                .and_then(|sword| sword.element())
+                .unwrap_or(Element::Physical)
+        }
+    }
+
+    /// How the receiver reacts to the element of this character's next attack.
+    pub fn attack_affinity(&self, receiver: &Self) -> Affinity {
+        receiver.class.affinity(self.attack_element())
     }
 
     pub fn physical_attack(&self) -> i32 {
         if self.class.is_magic() {
-            self.strength / 3
+            self.effective_strength() / 3
         } else {
             let sword_str = self.sword.as_ref().map_or(0, |s| s.strength());
-            self.strength + sword_str
+            self.effective_strength() + sword_str
         }
     }
 
     pub fn magic_attack(&self) -> i32 {
         if self.class.is_magic() {
-            self.strength * 3
+            self.effective_strength() * 3
         } else {
             0
         }
     }
 
+    /// The strength after applying the status effect modifiers.
+    fn effective_strength(&self) -> i32 {
+        match self.status_effect {
+            Some(StatusEffect::Weakness) => max(1, self.strength / 2),
+            _ => self.strength,
+        }
+    }
+
+    /// The speed after applying the equipment bonuses and the status effect modifiers.
+    pub fn effective_speed(&self) -> i32 {
+        let speed = self.speed
+            + self.ring.as_ref().map_or(0, |ring| ring.speed())
+            + self.affix_bonus(Affix::Speed);
+        match self.status_effect {
+            Some(StatusEffect::Haste) => speed * 3 / 2,
+            _ => speed,
+        }
+    }
+
     /// The character's class enables magic and there's enough mp left
     pub fn can_magic_attack(&self) -> bool {
         self.class.is_magic() && self.current_mp >= self.mp_cost()
     }
 
+    /// The class skills that are unlocked at the current level.
+    pub fn skills(&self) -> Vec<&class::Skill> {
+        self.class
+            .skills
+            .iter()
+            .filter(|skill| skill.level <= self.level)
+            .collect()
+    }
+
+    /// The skill is unlocked and there's enough mp left to use it.
+    pub fn can_use_skill(&self, skill: &class::Skill) -> bool {
+        skill.level <= self.level && self.current_mp >= self.skill_mp_cost(skill)
+    }
+
+    pub fn skill_mp_cost(&self, skill: &class::Skill) -> i32 {
+        skill.mp * self.mp_cost()
+    }
+
     fn mp_cost(&self) -> i32 {
         // each magic attack costs one third of the "canonical" mp total for this level
         self.class.mp.as_ref().map_or(0, |mp| mp.at(self.level) / 3)
@@ -251,6 +573,7 @@ impl Character {
         // we could incorporate strength here, but it's not clear if wouldn't just be noise
         // and it could also made it hard to make damage to stronger enemies
         self.shield.as_ref().map_or(0, |s| s.strength())
+            + self.helmet.as_ref().map_or(0, |h| h.strength())
     }
 
     /// How many experience points are gained by inflicting damage to an enemy.
@@ -269,25 +592,95 @@ impl Character {
     }
 
     /// Return the status that this character's attack should inflict on the receiver.
+    /// The class inflicted status takes precedence over the one of the sword,
+    /// and that over the poison affixes of the equipment.
     pub fn inflicted_status_effect(&self) -> Option<(StatusEffect, u32)> {
-        // at some point the player could generate it depending on the equipment
-        self.class.inflicts
+        self.class
+            .inflicts
+            .or_else(|| {
+                self.sword
+                    .as_ref()
+                    .and_then(|sword| sword.inflicted_status_effect())
+            })
+            .or_else(|| {
+                let poison = self.affix_bonus(Affix::Poison);
+                if poison > 0 {
+                    Some((StatusEffect::Poison, max(2, 100 / poison) as u32))
+                } else {
+                    None
+                }
+            })
+    }
+
+    /// Set the given status effect, replacing the current one if any.
+    pub fn set_status_effect(&mut self, status: StatusEffect) {
+        self.status_effect = Some(status);
+        self.status_effect_left = match status.duration() {
+            Duration::Turns(left) | Duration::Moves(left) => left,
+            Duration::UntilCured => 0,
+        };
     }
 
+    /// Remove the current status effect if it's a negative one.
     pub fn maybe_remove_status_effect(&mut self) -> bool {
-        if self.status_effect.is_some() {
+        match self.status_effect {
+            Some(status) if !status.is_positive() => {
+                self.status_effect = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Remove the current status effect only if it's the given one.
+    pub fn cure_status_effect(&mut self, status: StatusEffect) -> bool {
+        if self.status_effect == Some(status) {
             self.status_effect = None;
             return true;
         }
         false
     }
 
+    /// Count down the temporary status effect, removing it when it wears off.
+    /// A battle turn doesn't affect effects measured in moves; a move
+    /// immediately ends effects measured in turns.
+    pub fn countdown_status_effect(&mut self, in_battle: bool) {
+        if let Some(status) = self.status_effect {
+            match status.duration() {
+                Duration::Turns(_) if in_battle => self.status_effect_left -= 1,
+                Duration::Turns(_) => self.status_effect_left = 0,
+                Duration::Moves(_) if !in_battle => self.status_effect_left -= 1,
+                _ => return,
+            }
+
+            if self.status_effect_left <= 0 {
+                self.status_effect = None;
+            }
+        }
+    }
+
+    /// Whether the current status effect prevents the character from acting.
+    pub fn skips_turn(&self) -> bool {
+        matches!(self.status_effect, Some(status) if status.skips_turn())
+    }
+
+    /// If the character has a healing status effect, apply it.
+    pub fn receive_status_effect_heal(&mut self) -> Option<i32> {
+        if let Some(StatusEffect::Regen) = self.status_effect {
+            let amount = random().damage(max(1, self.effective_max_hp() / 20));
+            let recovered = self.heal(amount);
+            if recovered > 0 {
+                return Some(recovered);
+            }
+        }
+        None
+    }
+
     /// If the character suffers from a damage-producing status effect, apply it.
     pub fn receive_status_effect_damage(&mut self) -> Result<Option<i32>, Dead> {
-        // NOTE: in the future we could have a positive status that e.g. regen hp
         match self.status_effect {
             Some(StatusEffect::Burn) | Some(StatusEffect::Poison) => {
-                let damage = std::cmp::max(1, self.max_hp / 20);
+                let damage = std::cmp::max(1, self.effective_max_hp() / 20);
                 let damage = random().damage(damage);
                 self.receive_damage(damage)?;
                 Ok(Some(damage))
@@ -319,6 +712,11 @@ mod tests {
                 strength: Stat(10, 3),
                 speed: Stat(10, 2),
                 inflicts: None,
+                weak_to: vec![],
+                resists: vec![],
+                skills: vec![],
+                passives: class::Passives::default(),
+                drops: vec![],
             },
             1,
         )
@@ -393,6 +791,103 @@ mod tests {
         assert_eq!(40, foe.damage(&hero).0);
     }
 
+    #[test]
+    fn test_elemental_damage() {
+        let mut hero = new_char();
+        let mut foe = new_char();
+        hero.strength = 10;
+
+        assert_eq!(Element::Physical, hero.attack_element());
+        assert_eq!(Affinity::Neutral, hero.attack_affinity(&foe));
+        assert_eq!(10, hero.damage(&foe).0);
+
+        foe.class.weak_to = vec![Element::Physical];
+        assert_eq!(Affinity::Weak, hero.attack_affinity(&foe));
+        assert_eq!(15, hero.damage(&foe).0);
+
+        // the sword element replaces the physical one
+        let sword = equipment::Sword::elemental(1, Element::Fire);
+        let sword_strength = sword.strength();
+        hero.sword = Some(sword);
+        foe.class.resists = vec![Element::Fire];
+        assert_eq!(Element::Fire, hero.attack_element());
+        assert_eq!(Affinity::Resistant, hero.attack_affinity(&foe));
+        assert_eq!((10 + sword_strength) / 2, hero.damage(&foe).0);
+
+        // magic attacks are of the magic element
+        let mut mage = Character::player();
+        mage.change_class("mage").unwrap_or_default();
+        assert_eq!(Element::Magic, mage.attack_element());
+        mage.current_mp = 0;
+        assert_eq!(Element::Physical, mage.attack_element());
+    }
+
+    #[test]
+    fn test_skills() {
+        let mut hero = new_char();
+        let foe = new_char();
+        hero.strength = 10;
+
+        let skill = class::Skill {
+            name: "power strike".to_string(),
+            level: 2,
+            effect: class::SkillEffect::Attack,
+            power: 2,
+            element: None,
+            mp: 0,
+            cooldown: 3,
+        };
+        hero.class.skills.push(skill.clone());
+
+        assert!(hero.skills().is_empty());
+        assert!(!hero.can_use_skill(&skill));
+        hero.level = 2;
+        assert_eq!(1, hero.skills().len());
+        assert!(hero.can_use_skill(&skill));
+        assert_eq!(20, hero.skill_damage(&foe, &skill));
+
+        // magic skills need enough mp
+        let mut mage = Character::player();
+        mage.change_class("mage").unwrap_or_default();
+        let fireball = class::Skill {
+            name: "fireball".to_string(),
+            level: 1,
+            effect: class::SkillEffect::Attack,
+            power: 2,
+            element: Some(Element::Fire),
+            mp: 2,
+            cooldown: 0,
+        };
+        assert_eq!(2 * mage.mp_cost(), mage.skill_mp_cost(&fireball));
+        assert!(mage.can_use_skill(&fireball));
+        mage.current_mp = mage.skill_mp_cost(&fireball) - 1;
+        assert!(!mage.can_use_skill(&fireball));
+
+        // the skill element determines the affinity
+        let mut foe = new_char();
+        foe.class.weak_to = vec![Element::Fire];
+        let damage = mage.magic_attack() * 2 * 3 / 2;
+        assert_eq!(damage, mage.skill_damage(&foe, &fireball));
+    }
+
+    #[test]
+    fn test_passives() {
+        let mut hero = new_char();
+        let mut foe = new_char();
+        foe.strength = 20;
+
+        assert_eq!(20, foe.damage(&hero).0);
+        hero.class.passives.damage_reduction = 10;
+        assert_eq!(18, foe.damage(&hero).0);
+
+        hero.max_mp = 20;
+        hero.current_mp = 10;
+        assert_eq!(0, hero.regen_mp());
+        hero.class.passives.mp_regen = 10;
+        assert_eq!(2, hero.regen_mp());
+        assert_eq!(12, hero.current_mp);
+    }
+
     #[test]
     fn test_xp_gained() {
         let hero = new_char();
@@ -540,6 +1035,126 @@ mod tests {
         assert!(hero.is_dead());
     }
 
+    #[test]
+    fn test_status_effect_duration() {
+        let mut hero = new_char();
+
+        // lasts until cured
+        hero.set_status_effect(StatusEffect::Poison);
+        hero.countdown_status_effect(true);
+        hero.countdown_status_effect(false);
+        assert_eq!(Some(StatusEffect::Poison), hero.status_effect);
+        assert!(hero.maybe_remove_status_effect());
+        assert!(hero.status_effect.is_none());
+
+        // measured in turns
+        hero.set_status_effect(StatusEffect::Sleep);
+        assert!(hero.skips_turn());
+        hero.countdown_status_effect(true);
+        hero.countdown_status_effect(true);
+        assert!(hero.skips_turn());
+        hero.countdown_status_effect(true);
+        assert!(!hero.skips_turn());
+        assert!(hero.status_effect.is_none());
+
+        // a move ends the effects measured in turns
+        hero.set_status_effect(StatusEffect::Stun);
+        hero.countdown_status_effect(false);
+        assert!(hero.status_effect.is_none());
+
+        // measured in moves
+        hero.set_status_effect(StatusEffect::Haste);
+        for _ in 0..9 {
+            hero.countdown_status_effect(false);
+            hero.countdown_status_effect(true);
+        }
+        assert_eq!(Some(StatusEffect::Haste), hero.status_effect);
+        hero.countdown_status_effect(false);
+        assert!(hero.status_effect.is_none());
+
+        // positive effects are not removed
+        hero.set_status_effect(StatusEffect::Regen);
+        assert!(!hero.maybe_remove_status_effect());
+        assert!(!hero.cure_status_effect(StatusEffect::Poison));
+        assert!(hero.cure_status_effect(StatusEffect::Regen));
+    }
+
+    #[test]
+    fn test_equipment_bonuses() {
+        let mut hero = new_char();
+        hero.speed = 10;
+        let max_hp = hero.max_hp;
+        let max_mp = hero.max_mp;
+
+        let armor = equipment::Armor::new(5);
+        let hp = armor.hp();
+        hero.armor = Some(armor);
+        assert_eq!(max_hp + hp, hero.effective_max_hp());
+        hero.heal_full();
+        assert_eq!(max_hp + hp, hero.current_hp);
+
+        let amulet = equipment::Amulet::new(5);
+        let mp = amulet.mp();
+        hero.amulet = Some(amulet);
+        assert_eq!(max_mp + mp, hero.effective_max_mp());
+
+        let ring = equipment::Ring::new(5);
+        let speed = ring.speed();
+        hero.ring = Some(ring);
+        assert_eq!(10 + speed, hero.effective_speed());
+
+        let shield = equipment::Shield::new(5);
+        let helmet = equipment::Helmet::new(5);
+        let deffense = shield.strength() + helmet.strength();
+        assert!(helmet.strength() < shield.strength());
+        hero.shield = Some(shield);
+        hero.helmet = Some(helmet);
+        assert_eq!(deffense, hero.deffense());
+    }
+
+    #[test]
+    fn test_status_effect_modifiers() {
+        let mut hero = new_char();
+        hero.strength = 10;
+        hero.speed = 10;
+
+        hero.set_status_effect(StatusEffect::Weakness);
+        assert_eq!(5, hero.physical_attack());
+        assert_eq!(10, hero.effective_speed());
+
+        hero.set_status_effect(StatusEffect::Haste);
+        assert_eq!(10, hero.physical_attack());
+        assert_eq!(15, hero.effective_speed());
+
+        hero.set_status_effect(StatusEffect::Regen);
+        hero.current_hp = 10;
+        assert_eq!(Some(1), hero.receive_status_effect_heal());
+        assert_eq!(11, hero.current_hp);
+        assert!(matches!(hero.receive_status_effect_damage(), Ok(None)));
+    }
+
+    #[test]
+    fn test_inflicted_status_effect() {
+        let mut hero = new_char();
+        assert!(hero.inflicted_status_effect().is_none());
+
+        hero.sword = Some(equipment::Sword::new(1));
+        assert!(hero.inflicted_status_effect().is_none());
+
+        hero.sword = Some(equipment::Sword::elemental(1, Element::Fire));
+        assert_eq!(
+            Some(StatusEffect::Burn),
+            hero.inflicted_status_effect().map(|(status, _)| status)
+        );
+
+        // the class status takes precedence
+        hero.class.inflicts = Some((StatusEffect::Poison, 10));
+        assert_eq!(
+            Some((StatusEffect::Poison, 10)),
+            hero.inflicted_status_effect()
+        );
+    }
+
     #[test]
     fn test_class_change() {
         let mut player = Character::player();
@@ -1,6 +1,9 @@
 use crate::character;
+use crate::config::Config;
+use crate::datafile;
 use crate::game::Game;
 use crate::item;
+use crate::item::shop::Quantity;
 use crate::location::Location;
 use crate::log;
 use anyhow::{bail, Result};
@@ -36,21 +39,91 @@ pub enum Command {
 
     /// Inspect the directory contents, possibly finding treasure chests and hero tombstones.
     #[clap(name = "ls", display_order = 1)]
-    Inspect,
+    Inspect {
+        /// Use a key to open a locked chest, if there's one.
+        #[clap(long)]
+        unlock: bool,
+    },
 
-    /// Buys an item from the shop.
+    /// Buys items from the shop, e.g. `rpg buy potion:5 ether:2`.
     /// If name is omitted lists the items available for sale.
+    /// Nothing is bought if there isn't enough gold for all the items.
     #[clap(alias = "b", display_order = 2)]
-    Buy { items: Vec<String> },
+    Buy {
+        items: Vec<String>,
+
+        /// Buy as many units of the items without a quantity as the gold allows.
+        #[clap(long)]
+        max: bool,
+    },
+
+    /// Sells an item from the inventory or a piece of equipment from the stash.
+    #[clap(display_order = 2)]
+    Sell {
+        item: String,
+
+        /// How many of the item to sell.
+        #[clap(default_value = "1")]
+        quantity: usize,
+    },
+
+    /// Raises the level of the equipment worn in the given slot, for gold.
+    /// Upgrades may fail, and get less likely to succeed the further
+    /// the equipment is above the hero's level.
+    #[clap(display_order = 2)]
+    Upgrade {
+        slot: String,
+
+        /// Add a random bonus to the equipment instead of raising its level.
+        #[clap(long)]
+        enchant: bool,
+    },
+
+    /// Repairs the worn out equipment of the given slot, or all of it, for gold.
+    #[clap(display_order = 2)]
+    Repair { slot: Option<String> },
+
+    /// Rests at the inn of a town, restoring the hero for some gold.
+    #[clap(display_order = 2)]
+    Inn,
+
+    /// Crafts an item or an equipment bonus out of the materials dropped by enemies.
+    /// If the recipe is omitted lists the available recipes.
+    #[clap(display_order = 2)]
+    Craft { recipe: Vec<String> },
 
     /// Uses an item from the inventory.
     #[clap(alias = "u", display_order = 3)]
     Use { items: Vec<String> },
 
+    /// Puts on a piece of equipment from the stash, either by its full
+    /// description, e.g. sword[5]+poison, or the best one for a slot, e.g. sword.
+    #[clap(alias = "e", display_order = 3)]
+    Equip { name: String },
+
+    /// Takes off the equipment of the given slot and keeps it in the stash.
+    #[clap(display_order = 3)]
+    Unequip { slot: String },
+
+    /// Prints the equipment, stash and items of the hero.
+    #[clap(alias = "i", display_order = 3)]
+    Inventory,
+
     /// Prints the quest todo list.
     #[clap(alias = "t", display_order = 4)]
     Todo,
 
+    /// Lists the tombstones of fallen heroes and what remains in them.
+    Graveyard,
+
+    /// Lists the heroes exported by other players, which can show up as ghosts,
+    /// and the battles won and lost against them.
+    Ghosts {
+        /// Export the hero so other players can fight its ghost.
+        #[clap(long)]
+        export: bool,
+    },
+
     /// Resets the current game.
     Reset {
         /// Reset data files, losing cross-hero progress.
@@ -87,16 +160,28 @@ pub fn run(cmd: Option<Command>, game: &mut Game) -> Result<()> {
             bribe,
             force,
         } => change_dir(game, &destination, run, bribe, force)?,
-        Command::Inspect => game.inspect(),
+        Command::Inspect { unlock } => inspect(game, unlock)?,
         Command::Class { name } => class(game, &name)?,
         Command::Battle { run, bribe } => battle(game, run, bribe)?,
         Command::PrintWorkDir => println!("{}", game.location.path_string()),
         Command::Reset { .. } => game.reset(),
-        Command::Buy { items } => shop(game, &items)?,
+        Command::Buy { items, max } => shop(game, &items, max)?,
         Command::Use { items } => use_item(game, &items)?,
+        Command::Inn => game.rest_at_inn()?,
+        Command::Repair { slot } => {
+            item::shop::repair(game, slot.map(|slot| sanitize(&slot)).as_deref())?
+        }
+        Command::Upgrade { slot, enchant } => item::shop::upgrade(game, &sanitize(&slot), enchant)?,
+        Command::Craft { recipe } => craft(game, &recipe)?,
+        Command::Sell { item, quantity } => item::shop::sell(game, &sanitize(&item), quantity)?,
+        Command::Equip { name } => game.equip(&sanitize(&name))?,
+        Command::Unequip { slot } => game.unequip(&sanitize(&slot))?,
+        Command::Inventory => log::inventory(game),
         Command::Todo => {
             log::quest_list(game.quests.list());
         }
+        Command::Graveyard => graveyard(game),
+        Command::Ghosts { export } => ghosts(game, export)?,
     };
 
     Ok(())
@@ -115,6 +200,58 @@ fn change_dir(game: &mut Game, dest: &str, run: bool, bribe: bool, force: bool)
     Ok(())
 }
 
+/// Look for chests and tombstones at the current location,
+/// which could be dangerous.
+fn inspect(game: &mut Game, unlock: bool) -> Result<()> {
+    if let Err(character::Dead) = game.inspect() {
+        game.reset();
+        bail!("");
+    }
+    if unlock {
+        game.use_item("key")?;
+    }
+    Ok(())
+}
+
+/// List the tombstones of this user's heroes, and those of every user
+/// if there's a shared graveyard.
+fn graveyard(game: &mut Game) {
+    let shared = Config::get()
+        .shared_graveyard
+        .as_ref()
+        .and_then(|dir| datafile::load_shared_graveyard(dir).ok())
+        .unwrap_or_default();
+
+    let mut tombstones = game.graveyard();
+    for (location, tombstone) in shared.values().flatten() {
+        if !tombstone.is_expired() {
+            tombstones.push((location, tombstone));
+        }
+    }
+    tombstones.sort_by_key(|(_, tombstone)| std::cmp::Reverse(tombstone.timestamp));
+    log::graveyard(tombstones);
+}
+
+/// List the ghosts other players exported, or export the hero's own.
+fn ghosts(game: &Game, export: bool) -> Result<()> {
+    let dir = match &Config::get().ghosts {
+        Some(dir) => dir,
+        None => bail!("No ghosts directory configured."),
+    };
+
+    if export {
+        datafile::export_ghost(dir, &game.player)?;
+    } else {
+        let ghosts = datafile::load_ghosts(dir);
+        let ghosts = ghosts
+            .iter()
+            .map(|(owner, hero)| (owner.as_str(), hero, game.ghosts.get(owner)))
+            .collect();
+        log::ghosts(ghosts);
+    }
+    Ok(())
+}
+
 /// Potentially run a battle at the current location, independently from
 /// the hero's movement.
 fn battle(game: &mut Game, run: bool, bribe: bool) -> Result<()> {
@@ -143,17 +280,41 @@ fn class(game: &mut Game, class_name: &Option<String>) -> Result<()> {
     }
 }
 
-/// Buy an item from the shop or list the available items if no item name is provided.
+/// Buy items from the shop or list the available items if no item name is provided.
 /// Shopping is only allowed when the player is at the home directory.
-fn shop(game: &mut Game, items: &[String]) -> Result<()> {
+fn shop(game: &mut Game, items: &[String], max: bool) -> Result<()> {
     if items.is_empty() {
         item::shop::list(game)
     } else {
-        for item_name in items {
-            let item_name = sanitize(item_name);
-            item::shop::buy(game, &item_name)?
+        let orders = items
+            .iter()
+            .map(|item| parse_order(item, max))
+            .collect::<Result<Vec<_>>>()?;
+        item::shop::buy(game, &orders)
+    }
+}
+
+/// Parse an item to buy with an optional quantity, e.g. `potion:5`.
+fn parse_order(item: &str, max: bool) -> Result<(String, Quantity)> {
+    if let Some((name, quantity)) = item.split_once(':') {
+        match quantity.parse::<usize>() {
+            Ok(units) if units > 0 => Ok((sanitize(name), Quantity::Units(units))),
+            _ => bail!("Invalid quantity."),
         }
-        Ok(())
+    } else if max {
+        Ok((sanitize(item), Quantity::Max))
+    } else {
+        Ok((sanitize(item), Quantity::Units(1)))
+    }
+}
+
+/// Craft the given recipe, which may be spelled as several words,
+/// or list the recipes if none is provided.
+fn craft(game: &mut Game, recipe: &[String]) -> Result<()> {
+    if recipe.is_empty() {
+        item::craft::list(game)
+    } else {
+        item::craft::craft(game, &recipe.join(" ").to_lowercase())
     }
 }
 
@@ -179,6 +340,10 @@ fn sanitize(name: &str) -> String {
         "es" | "escape" => "escape",
         "sw" | "sword" => "sword",
         "sh" | "shield" => "shield",
+        "ar" | "armor" => "armor",
+        "he" | "helmet" => "helmet",
+        "ri" | "ring" => "ring",
+        "am" | "amulet" => "amulet",
         n => n,
     };
     name.to_string()
@@ -187,6 +352,7 @@ fn sanitize(name: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::item::equipment::{Equipment, Gear, Sword, DURABILITY};
 
     #[test]
     fn change_dir_battle() {
@@ -305,7 +471,7 @@ mod tests {
         run(Some(cmd), &mut game).unwrap();
 
         // inspect to pick up lost gold
-        let cmd = Command::Inspect;
+        let cmd = Command::Inspect { unlock: false };
         let result = run(Some(cmd), &mut game);
         assert!(result.is_ok());
         assert!(game.tombstones.is_empty());
@@ -322,6 +488,7 @@ mod tests {
         // not buy if not enough money
         let cmd = Command::Buy {
             items: vec![String::from("potion")],
+            max: false,
         };
         let result = run(Some(cmd), &mut game);
         assert!(result.is_err());
@@ -331,6 +498,7 @@ mod tests {
         game.gold = 200;
         let cmd = Command::Buy {
             items: vec![String::from("potion")],
+            max: false,
         };
         let result = run(Some(cmd), &mut game);
         assert!(result.is_ok());
@@ -359,9 +527,224 @@ mod tests {
         game.gold = 200;
         let cmd = Command::Buy {
             items: vec![String::from("potion")],
+            max: false,
         };
         let result = run(Some(cmd), &mut game);
         assert!(result.is_err());
         assert!(game.inventory().is_empty());
     }
+
+    #[test]
+    fn buy_quantities() {
+        let mut game = Game::new();
+
+        // nothing is bought if the gold isn't enough for all the items
+        game.gold = 1000;
+        let cmd = Command::Buy {
+            items: vec![String::from("p:3"), String::from("escape")],
+            max: false,
+        };
+        assert!(run(Some(cmd), &mut game).is_err());
+        assert!(game.inventory().is_empty());
+        assert_eq!(1000, game.gold);
+
+        let cmd = Command::Buy {
+            items: vec![String::from("p:3"), String::from("potion")],
+            max: false,
+        };
+        assert!(run(Some(cmd), &mut game).is_ok());
+        assert_eq!(4, *game.inventory().get("potion").unwrap());
+        assert_eq!(200, game.gold);
+
+        let cmd = Command::Buy {
+            items: vec![String::from("potion:0")],
+            max: false,
+        };
+        assert!(run(Some(cmd), &mut game).is_err());
+
+        let cmd = Command::Buy {
+            items: vec![String::from("potion:3000000000")],
+            max: false,
+        };
+        assert!(run(Some(cmd), &mut game).is_err());
+        assert_eq!(200, game.gold);
+
+        // buy as many as affordable
+        game.gold = 700;
+        let cmd = Command::Buy {
+            items: vec![String::from("potion")],
+            max: true,
+        };
+        assert!(run(Some(cmd), &mut game).is_ok());
+        assert_eq!(7, *game.inventory().get("potion").unwrap());
+        assert_eq!(100, game.gold);
+
+        // no more than the daily stock
+        game.gold = 2000;
+        let cmd = Command::Buy {
+            items: vec![String::from("potion:4")],
+            max: false,
+        };
+        assert!(run(Some(cmd), &mut game).is_err());
+        assert_eq!(2000, game.gold);
+
+        let cmd = Command::Buy {
+            items: vec![String::from("potion")],
+            max: true,
+        };
+        assert!(run(Some(cmd), &mut game).is_ok());
+        assert_eq!(10, *game.inventory().get("potion").unwrap());
+        assert_eq!(1400, game.gold);
+
+        let cmd = Command::Buy {
+            items: vec![String::from("potion")],
+            max: false,
+        };
+        assert!(run(Some(cmd), &mut game).is_err());
+    }
+
+    #[test]
+    fn buy_units_and_max() {
+        let mut game = Game::new();
+        game.gold = 5000;
+
+        // the units take the whole stock, so there's none left for max
+        let cmd = Command::Buy {
+            items: vec![String::from("potion:10"), String::from("potion")],
+            max: true,
+        };
+        let err = run(Some(cmd), &mut game).unwrap_err();
+        assert_eq!("Not enough stock.", err.to_string());
+        assert_eq!(5000, game.gold);
+
+        // max takes the stock left after the units
+        let cmd = Command::Buy {
+            items: vec![String::from("potion:4"), String::from("potion")],
+            max: true,
+        };
+        assert!(run(Some(cmd), &mut game).is_ok());
+        assert_eq!(10, *game.inventory().get("potion").unwrap());
+        assert_eq!(3000, game.gold);
+    }
+
+    #[test]
+    fn upgrade_equipment() {
+        let mut game = Game::new();
+        game.player.sword = Some(Sword::new(1));
+
+        let cmd = Command::Upgrade {
+            slot: String::from("sword"),
+            enchant: false,
+        };
+        assert!(run(Some(cmd), &mut game).is_err());
+        assert_eq!(1, game.player.sword.as_ref().unwrap().level());
+
+        // the price is that of the upgraded sword
+        game.gold = 1000;
+        let cmd = Command::Upgrade {
+            slot: String::from("sw"),
+            enchant: false,
+        };
+        assert!(run(Some(cmd), &mut game).is_ok());
+        assert_eq!(2, game.player.sword.as_ref().unwrap().level());
+        assert_eq!(0, game.gold);
+
+        game.gold = 1100;
+        let cmd = Command::Upgrade {
+            slot: String::from("sword"),
+            enchant: true,
+        };
+        assert!(run(Some(cmd), &mut game).is_ok());
+        assert_eq!(1, game.player.sword.as_ref().unwrap().affixes().len());
+        assert_eq!(0, game.gold);
+
+        game.gold = 10000;
+        let cmd = Command::Upgrade {
+            slot: String::from("shield"),
+            enchant: false,
+        };
+        assert!(run(Some(cmd), &mut game).is_err());
+        assert_eq!(10000, game.gold);
+    }
+
+    #[test]
+    fn repair_equipment() {
+        let mut game = Game::new();
+        game.player.sword = Some(Sword::new(1));
+
+        let cmd = Command::Repair { slot: None };
+        assert!(run(Some(cmd), &mut game).is_err());
+
+        // half the price for a sword about to break
+        let sword = game.player.sword.as_mut().unwrap();
+        for _ in 0..DURABILITY - 1 {
+            sword.wear();
+        }
+        let cmd = Command::Repair {
+            slot: Some(String::from("sword")),
+        };
+        assert!(run(Some(cmd), &mut game).is_err());
+
+        game.gold = 300;
+        let cmd = Command::Repair { slot: None };
+        assert!(run(Some(cmd), &mut game).is_ok());
+        assert_eq!(53, game.gold);
+        let sword = game.player.sword.as_ref().unwrap();
+        assert_eq!(Some(DURABILITY), sword.durability());
+    }
+
+    #[test]
+    fn sell_items() {
+        let mut game = Game::new();
+        game.add_item("potion", Box::new(item::Potion::new(1)));
+        game.add_item("potion", Box::new(item::Potion::new(1)));
+        game.add_item("key", Box::new(item::Key::new(1)));
+        game.stash.push(Gear::Sword(Sword::new(1)));
+
+        // nothing is sold if there's not enough
+        let cmd = Command::Sell {
+            item: String::from("p"),
+            quantity: 3,
+        };
+        assert!(run(Some(cmd), &mut game).is_err());
+        assert_eq!(2, *game.inventory().get("potion").unwrap());
+        let cmd = Command::Sell {
+            item: String::from("p"),
+            quantity: 0,
+        };
+        assert!(run(Some(cmd), &mut game).is_err());
+        assert_eq!(0, game.gold);
+
+        let cmd = Command::Sell {
+            item: String::from("p"),
+            quantity: 2,
+        };
+        assert!(run(Some(cmd), &mut game).is_ok());
+        assert!(!game.inventory().contains_key("potion"));
+        // includes +100g for sell item quest
+        assert_eq!(300, game.gold);
+
+        // the shop doesn't buy keys
+        let cmd = Command::Sell {
+            item: String::from("key"),
+            quantity: 1,
+        };
+        assert!(run(Some(cmd), &mut game).is_err());
+        assert_eq!(1, *game.inventory().get("key").unwrap());
+
+        // sell equipment from the stash
+        let cmd = Command::Sell {
+            item: String::from("sword"),
+            quantity: 1,
+        };
+        assert!(run(Some(cmd), &mut game).is_ok());
+        assert!(game.stash.is_empty());
+        assert_eq!(550, game.gold);
+
+        let cmd = Command::Sell {
+            item: String::from("sword"),
+            quantity: 1,
+        };
+        assert!(run(Some(cmd), &mut game).is_err());
+    }
 }
@@ -0,0 +1,98 @@
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+
+/// Game settings, which can be customized by placing
+/// a yaml file at `~/.rpg/config.yaml`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Config {
+    pub chest_respawn: ChestRespawn,
+    pub tombstone_decay: TombstoneDecay,
+
+    /// A directory where the tombstones are stored instead of the game data,
+    /// so they can be picked up by the heroes of every user on the machine.
+    pub shared_graveyard: Option<std::path::PathBuf>,
+
+    /// A directory where heroes are exported, to be fought as ghosts
+    /// by the heroes of other players.
+    pub ghosts: Option<std::path::PathBuf>,
+
+    pub towns: Towns,
+
+    /// Whether swords and shields wear out with use, breaking unless
+    /// they are repaired at the shop.
+    pub durability: bool,
+}
+
+/// Directories other than home with a shop, an inn and a save point,
+/// so the hero doesn't need to go all the way back home.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Towns {
+    /// Path patterns of the town locations, e.g. `~/work/*`.
+    pub locations: Vec<String>,
+
+    /// A file that turns the directory containing it into a town.
+    pub marker: Option<String>,
+
+    /// Gold charged per hero level to rest at the inn.
+    pub inn_cost: i32,
+}
+
+impl Default for Towns {
+    fn default() -> Self {
+        Self {
+            locations: Vec::new(),
+            marker: Some(".rpg-town".to_string()),
+            inn_cost: 25,
+        }
+    }
+}
+
+/// How long it takes for a looted chest to show up again at the same location.
+/// It respawns when either of the conditions is met, and a zero value disables
+/// that condition.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ChestRespawn {
+    pub hours: u64,
+    pub battles: u32,
+}
+
+impl Default for ChestRespawn {
+    fn default() -> Self {
+        Self {
+            hours: 24,
+            battles: 25,
+        }
+    }
+}
+
+/// How long it takes for the contents of a tombstone to fully decay, either in days
+/// or in deaths of later heroes, whichever comes first. A zero value disables
+/// that kind of decay.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct TombstoneDecay {
+    pub days: u64,
+    pub deaths: u32,
+}
+
+impl Default for TombstoneDecay {
+    fn default() -> Self {
+        Self { days: 7, deaths: 3 }
+    }
+}
+
+static CONFIG: OnceCell<Config> = OnceCell::new();
+
+impl Config {
+    /// Customize the settings based on an input yaml byte array.
+    pub fn load(bytes: &[u8]) {
+        CONFIG.set(serde_yaml::from_slice(bytes).unwrap()).unwrap();
+    }
+
+    pub fn get() -> &'static Self {
+        CONFIG.get_or_init(Config::default)
+    }
+}
@@ -1,12 +1,17 @@
-use crate::character::class;
+use crate::character::{boss, class, Character};
+use crate::config;
 use crate::game;
-use std::{fs, io, path};
+use crate::game::tombstone::SharedGraveyard;
+use crate::item::craft;
+use crate::location::biome;
+use std::{fs, io, path, thread, time};
 
 pub struct NotFound;
 
 pub fn load() -> Result<game::Game, NotFound> {
     let data: Vec<u8> = read(data_file())?;
-    let game = serde_json::from_slice(&data).unwrap();
+    let mut game: game::Game = serde_json::from_slice(&data).unwrap();
+    game.quests.add_boss_quests(game.player.level);
     Ok(game)
 }
 
@@ -22,12 +27,201 @@ pub fn remove() {
     }
 }
 
+pub fn load_config() {
+    if let Ok(bytes) = read(config_file()) {
+        config::Config::load(&bytes)
+    }
+}
+
 pub fn load_classes() {
     if let Ok(bytes) = read(classes_file()) {
         class::Class::load(&bytes)
     }
 }
 
+pub fn load_bosses() {
+    if let Ok(bytes) = read(bosses_file()) {
+        boss::Boss::load(&bytes)
+    }
+}
+
+pub fn load_biomes() {
+    if let Ok(bytes) = read(biomes_file()) {
+        biome::Biome::load(&bytes)
+    }
+}
+
+pub fn load_recipes() {
+    if let Ok(bytes) = read(recipes_file()) {
+        craft::Recipe::load(&bytes)
+    }
+}
+
+/// Load the tombstones of every user from the shared graveyard, which is
+/// empty until someone dies. A file that can't be parsed is an error instead,
+/// so it's not overwritten and the tombstones of other users are kept.
+pub fn load_shared_graveyard(dir: &path::Path) -> Result<SharedGraveyard, io::Error> {
+    let data = match fs::read(dir.join("graveyard")) {
+        Ok(data) => data,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(SharedGraveyard::new()),
+        Err(err) => return Err(err),
+    };
+    serde_json::from_slice(&data).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// Apply the given function to the shared graveyard and save the changes.
+/// The file is locked while doing so, since multiple users may be accessing it.
+pub fn update_shared_graveyard<F, T>(dir: &path::Path, f: F) -> Result<T, io::Error>
+where
+    F: FnOnce(&mut SharedGraveyard) -> T,
+{
+    let _lock = FileLock::acquire(dir.join("graveyard.lock"))?;
+
+    let mut graveyard = load_shared_graveyard(dir)?;
+    let result = f(&mut graveyard);
+
+    // replace the file at once, so a crash while writing can't truncate it
+    let temp = dir.join("graveyard.tmp");
+    fs::write(&temp, serde_json::to_vec(&graveyard).unwrap())?;
+    share(&temp);
+    fs::rename(&temp, dir.join("graveyard"))?;
+    Ok(result)
+}
+
+/// How long a process can hold a lock. Older locks are assumed to be
+/// left behind by a crashed process, and taken over.
+const LOCK_TIMEOUT: time::Duration = time::Duration::from_secs(10);
+
+/// A lock file that only one process can create at a time,
+/// removed when dropped.
+struct FileLock(path::PathBuf);
+
+impl FileLock {
+    fn acquire(path: path::PathBuf) -> Result<Self, io::Error> {
+        for _ in 0..50 {
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(_) => {
+                    share(&path);
+                    return Ok(Self(path));
+                }
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                    // a crashed process may have left the lock behind
+                    if !remove_if_stale(&path) {
+                        thread::sleep(time::Duration::from_millis(100));
+                    }
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "the shared graveyard is locked",
+        ))
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        fs::remove_file(&self.0).unwrap_or_default();
+    }
+}
+
+/// Remove the lock at the given path if it's older than the timeout. It's moved
+/// aside first, so only one of the waiting processes can remove it, and put back
+/// if it turns out another process replaced it with a fresh one in the meantime.
+fn remove_if_stale(path: &path::Path) -> bool {
+    let timestamp = match modified(path) {
+        Some(timestamp) => timestamp,
+        None => return false,
+    };
+    let is_stale = timestamp
+        .elapsed()
+        .map(|elapsed| elapsed > LOCK_TIMEOUT)
+        .unwrap_or_default();
+    if !is_stale {
+        return false;
+    }
+
+    let aside = path.with_extension(format!("stale.{}", std::process::id()));
+    if fs::rename(path, &aside).is_err() {
+        return false;
+    }
+    let removed = modified(&aside) == Some(timestamp);
+    if !removed {
+        fs::hard_link(&aside, path).unwrap_or_default();
+    }
+    fs::remove_file(&aside).unwrap_or_default();
+    removed
+}
+
+fn modified(path: &path::Path) -> Option<time::SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Let other users read and write the given file.
+#[cfg(unix)]
+fn share(path: &path::Path) {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o666)).unwrap_or_default();
+}
+
+#[cfg(not(unix))]
+fn share(_path: &path::Path) {}
+
+/// Load the heroes exported by other players to the given directory,
+/// along with the name of their player.
+pub fn load_ghosts(dir: &path::Path) -> Vec<(String, Character)> {
+    let username = username();
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != "json" {
+                return None;
+            }
+            let owner = path.file_stem()?.to_string_lossy().to_string();
+            if owner == username {
+                return None;
+            }
+            let hero = serde_json::from_slice(&read(path).ok()?).ok()?;
+            Some((owner, hero))
+        })
+        .collect()
+}
+
+/// Save the hero to the given directory, replacing the one previously
+/// exported by this user, if any.
+pub fn export_ghost(dir: &path::Path, hero: &Character) -> Result<(), io::Error> {
+    let file = dir.join(format!("{}.json", username()));
+    fs::write(&file, serde_json::to_vec(hero).unwrap())?;
+    share(&file);
+    Ok(())
+}
+
+/// The name of the user playing the game.
+pub fn username() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| {
+            dirs::home_dir()
+                .and_then(|home| {
+                    home.file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                })
+                .unwrap_or_default()
+        })
+}
+
 fn read(file: path::PathBuf) -> Result<Vec<u8>, NotFound> {
     fs::read(file).map_err(|_| NotFound)
 }
@@ -48,6 +242,64 @@ fn data_file() -> path::PathBuf {
     rpg_dir().join("data")
 }
 
+fn config_file() -> path::PathBuf {
+    rpg_dir().join("config.yaml")
+}
+
 fn classes_file() -> path::PathBuf {
     rpg_dir().join("classes.yaml")
 }
+
+fn bosses_file() -> path::PathBuf {
+    rpg_dir().join("bosses.yaml")
+}
+
+fn biomes_file() -> path::PathBuf {
+    rpg_dir().join("biomes.yaml")
+}
+
+fn recipes_file() -> path::PathBuf {
+    rpg_dir().join("recipes.yaml")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_file_lock() {
+        let dir = std::env::temp_dir().join(format!("rpg-lock-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("graveyard.lock");
+
+        // a second acquire waits until the first lock is dropped
+        let lock = FileLock::acquire(path.clone()).unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let waiting = path.clone();
+        let waiter = thread::spawn(move || {
+            let lock = FileLock::acquire(waiting);
+            sender.send(()).unwrap();
+            lock.is_ok()
+        });
+        assert!(receiver
+            .recv_timeout(time::Duration::from_millis(500))
+            .is_err());
+        drop(lock);
+        assert!(waiter.join().unwrap());
+
+        // locks older than the timeout are taken over
+        let lock = FileLock::acquire(path.clone()).unwrap();
+        let old = time::SystemTime::now() - LOCK_TIMEOUT * 2;
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(old))
+            .unwrap();
+        assert!(FileLock::acquire(path.clone()).is_ok());
+        // the taken over lock no longer owns the file
+        std::mem::forget(lock);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,4 +1,4 @@
-use crate::character::Character;
+use crate::character::{Affinity, Character, StatusEffect};
 use crate::game;
 use crate::game::battle;
 use crate::location::Location;
@@ -27,15 +27,34 @@ pub enum Event<'a> {
         kind: battle::AttackType,
         damage: i32,
         mp_cost: i32,
+        affinity: Affinity,
     },
     EnemyAttack {
         kind: battle::AttackType,
         damage: i32,
         mp_cost: i32,
+        affinity: Affinity,
     },
     StatusEffectDamage {
         damage: i32,
     },
+    EnemyStatusEffectDamage {
+        enemy: &'a Character,
+        damage: i32,
+    },
+    SkillUsed {
+        name: &'a str,
+    },
+    Steal {
+        items: &'a [String],
+    },
+    StatusEffectHeal {
+        recovered_hp: i32,
+    },
+    PlayerTurnSkipped,
+    EnemyTurnSkipped {
+        enemy: &'a Character,
+    },
     BattleWon {
         enemy: &'a Character,
         location: Location,
@@ -50,7 +69,7 @@ pub enum Event<'a> {
         current: i32,
     },
     Heal {
-        item: Option<&'static str>,
+        item: Option<&'a str>,
         recovered_hp: i32,
         recovered_mp: i32,
         healed: bool,
@@ -61,11 +80,39 @@ pub enum Event<'a> {
     ItemUsed {
         item: String,
     },
+    Buff {
+        item: &'a str,
+        status: StatusEffect,
+    },
+    ItemSold {
+        item: String,
+        gold: i32,
+    },
+    ItemCrafted {
+        recipe: String,
+    },
+    EquipmentBroken {
+        item: String,
+    },
+    ItemUpgraded {
+        item: String,
+        cost: i32,
+        success: bool,
+    },
     ChestFound {
         items: &'a [String],
         gold: i32,
         is_tombstone: bool,
     },
+    ChestLocked {
+        level: i32,
+    },
+    MimicDetected,
+    TrapDisarmed,
+    TrapTriggered {
+        damage: i32,
+        status: Option<StatusEffect>,
+    },
     ClassChanged {
         lost_xp: i32,
     },
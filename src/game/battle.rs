@@ -1,7 +1,12 @@
+use super::chest::Chest;
 use super::Game;
+use crate::character::class::{Skill, SkillEffect};
 use crate::character::{Character, Dead, StatusEffect};
+use crate::config::Config;
 use crate::event::Event;
+use crate::item::equipment::Affix;
 use crate::randomizer::Randomizer;
+use std::collections::HashMap;
 
 /// Outcome of an attack attempt.
 /// This affects primarily how the attack is displayed.
@@ -12,30 +17,41 @@ pub enum AttackType {
     Miss,
 }
 
-/// Run a turn-based combat between the game's player and the given enemy.
+/// Run a turn-based combat between the game's player and the given enemy,
+/// which carries the given loot that the player may steal before winning it.
 /// Return Ok(xp gained) if the player wins, or Err(()) if it loses.
-pub fn run(game: &mut Game, enemy: &mut Character, random: &dyn Randomizer) -> Result<i32, Dead> {
+pub fn run(
+    game: &mut Game,
+    enemy: &mut Character,
+    loot: &mut Option<Chest>,
+    random: &dyn Randomizer,
+) -> Result<i32, Dead> {
     // These accumulators get increased based on the characters speed:
     // the faster will get more frequent turns.
     // This could be generalized to player vs enemy parties
     let (mut pl_accum, mut en_accum) = (0, 0);
     let mut xp = 0;
 
+    // turns left before each of the player skills can be used again
+    let mut cooldowns = HashMap::new();
+
     while !enemy.is_dead() {
-        pl_accum += game.player.speed;
-        en_accum += enemy.speed;
+        pl_accum += game.player.effective_speed();
+        en_accum += enemy.effective_speed();
 
         if pl_accum >= en_accum {
-            if !autopotion(game, enemy) && !autoether(game, enemy) {
-                let new_xp = player_attack(game, enemy, random);
-                xp += new_xp;
-            }
-
+            xp += player_turn(game, enemy, loot, &mut cooldowns, random);
             game.maybe_receive_status_damage()?;
+            game.player.countdown_status_effect(true);
             pl_accum = -1;
         } else {
-            enemy_attack(game, enemy, random)?;
-            // TODO enemy receive status effect
+            if enemy.skips_turn() {
+                Event::emit(game, Event::EnemyTurnSkipped { enemy });
+            } else {
+                enemy_attack(game, enemy, random)?;
+            }
+            xp += enemy_receive_status_damage(game, enemy);
+            enemy.countdown_status_effect(true);
             en_accum = -1;
         }
     }
@@ -43,11 +59,53 @@ pub fn run(game: &mut Game, enemy: &mut Character, random: &dyn Randomizer) -> R
     Ok(xp)
 }
 
+/// Use a skill, an item or attack the enemy, returning the gained experience.
+/// The given cooldowns are the turns left before each skill is ready again.
+fn player_turn(
+    game: &mut Game,
+    enemy: &mut Character,
+    loot: &mut Option<Chest>,
+    cooldowns: &mut HashMap<String, i32>,
+    random: &dyn Randomizer,
+) -> i32 {
+    let mut used_skill = None;
+    let xp = if game.player.skips_turn() {
+        Event::emit(game, Event::PlayerTurnSkipped);
+        0
+    } else if autopotion(game, enemy) || autoether(game, enemy) {
+        0
+    } else if let Some(skill) = pick_skill(game, enemy, cooldowns) {
+        let xp = use_skill(game, enemy, loot, &skill, random);
+        used_skill = Some(skill);
+        xp
+    } else {
+        player_attack(game, enemy, random)
+    };
+
+    // the cooldown of the skill just used starts counting on the next turn
+    for turns in cooldowns.values_mut() {
+        *turns -= 1;
+    }
+    if let Some(skill) = used_skill {
+        cooldowns.insert(skill.name.clone(), skill.cooldown);
+    }
+    xp
+}
+
 /// Attack enemy, returning the gained experience
 fn player_attack(game: &mut Game, enemy: &mut Character, random: &dyn Randomizer) -> i32 {
+    let affinity = game.player.attack_affinity(enemy);
     let (attack_type, damage, mp_cost, new_xp) = generate_attack(&game.player, enemy, random);
     enemy.receive_damage(damage).unwrap_or_default();
     game.player.current_mp -= mp_cost;
+    let recovered_hp = game.player.steal_life(damage);
+    if !matches!(attack_type, AttackType::Miss) {
+        wear_out(game, "sword");
+    }
+
+    if let AttackType::Effect(status) = attack_type {
+        enemy.set_status_effect(status);
+    }
 
     Event::emit(
         game,
@@ -56,23 +114,120 @@ fn player_attack(game: &mut Game, enemy: &mut Character, random: &dyn Randomizer
             kind: attack_type,
             damage,
             mp_cost,
+            affinity,
         },
     );
+
+    if recovered_hp > 0 {
+        Event::emit(
+            game,
+            Event::Heal {
+                item: Some("lifesteal"),
+                recovered_hp,
+                recovered_mp: 0,
+                healed: false,
+            },
+        );
+    }
     new_xp
 }
 
+/// Choose the first of the player skills that is ready to use and makes
+/// sense in the current situation, if any.
+fn pick_skill(game: &Game, enemy: &Character, cooldowns: &HashMap<String, i32>) -> Option<Skill> {
+    let player = &game.player;
+    player
+        .skills()
+        .into_iter()
+        .filter(|skill| player.can_use_skill(skill))
+        .filter(|skill| cooldowns.get(&skill.name).copied().unwrap_or(0) <= 0)
+        .find(|skill| match skill.effect {
+            SkillEffect::Attack => true,
+            SkillEffect::Heal => player.current_hp <= player.effective_max_hp() / 2,
+            // there's no point in stealing if the enemy is about to die
+            SkillEffect::Steal => player.damage(enemy).0 < enemy.current_hp,
+        })
+        .cloned()
+}
+
+/// Use the given skill on the enemy, returning the gained experience.
+fn use_skill(
+    game: &mut Game,
+    enemy: &mut Character,
+    loot: &mut Option<Chest>,
+    skill: &Skill,
+    random: &dyn Randomizer,
+) -> i32 {
+    let mp_cost = game.player.skill_mp_cost(skill);
+    game.player.current_mp -= mp_cost;
+
+    match skill.effect {
+        SkillEffect::Attack => {
+            let affinity = skill.element.map_or_else(
+                || game.player.attack_affinity(enemy),
+                |element| enemy.class.affinity(element),
+            );
+            let damage = random.damage(game.player.skill_damage(enemy, skill));
+            let xp = game.player.xp_gained(enemy, damage);
+            enemy.receive_damage(damage).unwrap_or_default();
+            wear_out(game, "sword");
+
+            Event::emit(game, Event::SkillUsed { name: &skill.name });
+            Event::emit(
+                game,
+                Event::PlayerAttack {
+                    enemy,
+                    kind: AttackType::Regular,
+                    damage,
+                    mp_cost,
+                    affinity,
+                },
+            );
+            xp
+        }
+        SkillEffect::Heal => {
+            let recovered_hp = game
+                .player
+                .heal(game.player.effective_max_hp() * skill.power / 100);
+            Event::emit(
+                game,
+                Event::Heal {
+                    item: Some(&skill.name),
+                    recovered_hp,
+                    recovered_mp: 0,
+                    healed: false,
+                },
+            );
+            0
+        }
+        SkillEffect::Steal => {
+            let items = loot
+                .as_mut()
+                .and_then(Chest::take_one)
+                .map_or(Vec::new(), |mut chest| chest.pick_up(game).0);
+            Event::emit(game, Event::Steal { items: &items });
+            0
+        }
+    }
+}
+
 /// Attack player, returning Err(Dead) if the player dies.
 fn enemy_attack(
     game: &mut Game,
     enemy: &mut Character,
     random: &dyn Randomizer,
 ) -> Result<(), Dead> {
+    let affinity = enemy.attack_affinity(&game.player);
     let (attack_type, damage, mp_cost, _xp) = generate_attack(enemy, &game.player, random);
     let result = game.player.receive_damage(damage);
     enemy.current_mp -= mp_cost;
+    enemy.steal_life(damage);
+    if !matches!(attack_type, AttackType::Miss) {
+        wear_out(game, "shield");
+    }
 
     if let AttackType::Effect(status) = attack_type {
-        game.player.status_effect = Some(status);
+        game.player.set_status_effect(status);
     }
 
     Event::emit(
@@ -81,11 +236,47 @@ fn enemy_attack(
             kind: attack_type,
             damage,
             mp_cost,
+            affinity,
         },
     );
     result
 }
 
+/// Use up the player equipment of the given slot, if durability is enabled,
+/// breaking it once there are no uses left.
+fn wear_out(game: &mut Game, slot: &str) {
+    if !Config::get().durability {
+        return;
+    }
+
+    if let Some(equipment) = game.player.equipment_mut(slot) {
+        equipment.wear();
+        if equipment.durability() == Some(0) {
+            if let Some(gear) = game.player.unequip(slot) {
+                Event::emit(
+                    game,
+                    Event::EquipmentBroken {
+                        item: gear.to_string(),
+                    },
+                );
+            }
+        }
+    }
+}
+
+/// Enemy takes damage from status effects, if any, returning the experience
+/// gained by the player for it.
+fn enemy_receive_status_damage(game: &mut Game, enemy: &mut Character) -> i32 {
+    let previous_hp = enemy.current_hp;
+    enemy.receive_status_effect_damage().unwrap_or_default();
+    let damage = previous_hp - enemy.current_hp;
+
+    if damage > 0 {
+        Event::emit(game, Event::EnemyStatusEffectDamage { enemy, damage });
+    }
+    game.player.xp_gained(enemy, damage)
+}
+
 /// Return randomized attack parameters according to the character attributes.
 fn generate_attack(
     attacker: &Character,
@@ -98,8 +289,9 @@ fn generate_attack(
 
     let attack_type = random.attack_type(
         attacker.inflicted_status_effect(),
-        attacker.speed,
-        receiver.speed,
+        attacker.effective_speed(),
+        receiver.effective_speed(),
+        attacker.affix_bonus(Affix::Critical),
     );
 
     match attack_type {
@@ -117,7 +309,7 @@ fn generate_attack(
 /// If the player is low on hp and has a potion available use it
 /// instead of attacking in the current turn.
 fn autopotion(game: &mut Game, enemy: &Character) -> bool {
-    if game.player.current_hp > game.player.max_hp / 3 {
+    if game.player.current_hp > game.player.effective_max_hp() / 3 {
         return false;
     }
 
@@ -152,6 +344,8 @@ mod tests {
     use crate::character;
     use crate::character::class;
     use crate::character::enemy;
+    use crate::item::equipment::{Equipment, Sword};
+    use crate::item::Potion;
     use crate::randomizer::random;
 
     #[test]
@@ -164,10 +358,13 @@ mod tests {
         game.player.speed = 2;
         game.player.current_hp = 20;
         game.player.strength = 10; // each hit will take 10hp
+        game.player.class.passives = class::Passives::default();
 
         enemy.speed = 1;
         enemy.current_hp = 15;
         enemy.strength = 5;
+        // affinities would alter the expected damage
+        enemy.class.resists.clear();
 
         // expected turns
         // enemy - 10hp
@@ -188,6 +385,8 @@ mod tests {
         enemy.speed = 1;
         enemy.current_hp = 15;
         enemy.strength = 5;
+        // affinities would alter the expected damage
+        enemy.class.resists.clear();
 
         // same turns, added xp increases level
 
@@ -211,9 +410,12 @@ mod tests {
     #[test]
     fn magic_attacks() {
         let mut game = Game::new();
-        let mut enemy = enemy::at(&game.location, &game.player);
+        let mut enemy = enemy::at(&game.location, game.location.features(), &game.player);
         enemy.max_hp = 100;
         enemy.current_hp = 100;
+        enemy.class.weak_to.clear();
+        enemy.class.resists.clear();
+        enemy.class.passives = class::Passives::default();
 
         game.player.change_class("mage").unwrap_or_default();
         game.player.max_mp = 10;
@@ -235,4 +437,143 @@ mod tests {
         assert_eq!(1, game.player.current_mp);
         assert_eq!(7, enemy.current_hp);
     }
+
+    #[test]
+    fn skills() {
+        let mut game = Game::new();
+        let mut enemy =
+            character::Character::new(class::Class::random(class::Category::Common).clone(), 1);
+        enemy.max_hp = 100;
+        enemy.current_hp = 100;
+        enemy.class.weak_to.clear();
+        enemy.class.resists.clear();
+
+        game.player.strength = 10;
+        game.player.class.skills = vec![
+            Skill {
+                name: "heal".to_string(),
+                level: 1,
+                effect: SkillEffect::Heal,
+                power: 50,
+                element: None,
+                mp: 0,
+                cooldown: 0,
+            },
+            Skill {
+                name: "power strike".to_string(),
+                level: 1,
+                effect: SkillEffect::Attack,
+                power: 2,
+                element: None,
+                mp: 0,
+                cooldown: 3,
+            },
+        ];
+
+        // heal is skipped with enough hp
+        let mut cooldowns = HashMap::new();
+        let skill = pick_skill(&game, &enemy, &cooldowns).unwrap();
+        assert_eq!("power strike", skill.name);
+        player_turn(&mut game, &mut enemy, &mut None, &mut cooldowns, &random());
+        assert_eq!(80, enemy.current_hp);
+
+        // the skill can't be used during the cooldown turns
+        for _ in 0..skill.cooldown {
+            assert!(pick_skill(&game, &enemy, &cooldowns).is_none());
+            player_turn(&mut game, &mut enemy, &mut None, &mut cooldowns, &random());
+        }
+        assert_eq!(50, enemy.current_hp);
+        let skill = pick_skill(&game, &enemy, &cooldowns).unwrap();
+        assert_eq!("power strike", skill.name);
+
+        game.player.current_hp = 1;
+        let skill = pick_skill(&game, &enemy, &cooldowns).unwrap();
+        assert_eq!("heal", skill.name);
+        use_skill(&mut game, &mut enemy, &mut None, &skill, &random());
+        assert_eq!(1 + game.player.max_hp / 2, game.player.current_hp);
+    }
+
+    #[test]
+    fn steal() {
+        let mut game = Game::new();
+        let mut enemy =
+            character::Character::new(class::Class::random(class::Category::Common).clone(), 1);
+        let skill = Skill {
+            name: "steal".to_string(),
+            level: 1,
+            effect: SkillEffect::Steal,
+            power: 0,
+            element: None,
+            mp: 0,
+            cooldown: 0,
+        };
+
+        let mut other = Game::new();
+        other.add_item("potion", Box::new(Potion::new(1)));
+        other.add_item("potion", Box::new(Potion::new(1)));
+        other.player.sword = Some(Sword::new(1));
+        let mut loot = Some(Chest::drop(&mut other));
+
+        // the stolen item is taken from the enemy loot
+        use_skill(&mut game, &mut enemy, &mut loot, &skill, &random());
+        assert_eq!(1, game.inventory().values().sum::<usize>());
+        use_skill(&mut game, &mut enemy, &mut loot, &skill, &random());
+        assert_eq!(2, game.inventory().values().sum::<usize>());
+
+        // and what's left is won after the battle
+        loot.unwrap().pick_up(&mut game);
+        assert_eq!(2, *game.inventory().get("potion").unwrap());
+        assert!(game.player.sword.is_some());
+        assert!(game.stash.is_empty());
+
+        // nothing to steal from an enemy without loot
+        use_skill(&mut game, &mut enemy, &mut None, &skill, &random());
+        assert_eq!(2, *game.inventory().get("potion").unwrap());
+    }
+
+    #[test]
+    fn enemy_status_effect() {
+        let mut game = Game::new();
+        let enemy_class = class::Class::random(class::Category::Common);
+        let mut enemy = character::Character::new(enemy_class.clone(), 1);
+        enemy.max_hp = 100;
+        enemy.current_hp = 100;
+
+        // no status, no damage
+        assert_eq!(0, enemy_receive_status_damage(&mut game, &mut enemy));
+        assert_eq!(100, enemy.current_hp);
+
+        enemy.status_effect = Some(StatusEffect::Burn);
+        let xp = enemy_receive_status_damage(&mut game, &mut enemy);
+        assert_eq!(95, enemy.current_hp);
+        assert_eq!(5, xp);
+
+        // the enemy can die of the status effect
+        enemy.current_hp = 3;
+        enemy_receive_status_damage(&mut game, &mut enemy);
+        assert!(enemy.is_dead());
+    }
+
+    #[test]
+    fn skip_turns() {
+        let mut game = Game::new();
+        let enemy_class = class::Class::random(class::Category::Common);
+        let mut enemy = character::Character::new(enemy_class.clone(), 1);
+
+        // same setup as the won test, but the enemy sleeps through the battle
+        game.player.speed = 2;
+        game.player.current_hp = 20;
+        game.player.strength = 10;
+
+        enemy.speed = 1;
+        enemy.current_hp = 15;
+        enemy.strength = 5;
+        // affinities would alter the expected damage
+        enemy.class.resists.clear();
+        enemy.set_status_effect(StatusEffect::Sleep);
+
+        let result = game.battle(&mut enemy);
+        assert!(result.is_ok());
+        assert_eq!(20, game.player.current_hp);
+    }
 }
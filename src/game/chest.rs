@@ -1,8 +1,10 @@
+use crate::character::boss::Boss;
+use crate::character::{Element, StatusEffect};
 use crate::game;
-use crate::item::equipment::{Shield, Sword};
-use crate::item::{equipment::Equipment, Escape, Ether, Item, Potion, Remedy};
-use crate::randomizer::random;
+use crate::item::equipment::{keep_best, Affix, Amulet, Armor, Gear, Helmet, Ring, Shield, Sword};
+use crate::item::{equipment::Equipment, Escape, Ether, Item, Key, Potion, Remedy};
 use crate::randomizer::Randomizer;
+use crate::randomizer::{random, seeded};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -14,34 +16,92 @@ pub struct Chest {
     items: HashMap<String, Vec<Box<dyn Item>>>,
     sword: Option<Sword>,
     shield: Option<Shield>,
+    #[serde(default)]
+    armor: Option<Armor>,
+    #[serde(default)]
+    helmet: Option<Helmet>,
+    #[serde(default)]
+    ring: Option<Ring>,
+    #[serde(default)]
+    amulet: Option<Amulet>,
+
+    /// Extra equipment, e.g. the stash of a fallen hero.
+    #[serde(default)]
+    bag: Vec<Gear>,
     gold: i32,
+
+    #[serde(skip)]
+    hazard: Option<Hazard>,
+
+    /// The minimum level of the key required to open the chest, if locked.
+    #[serde(skip)]
+    lock: Option<i32>,
+}
+
+/// Something nasty waiting for the hero that opens a chest.
+#[derive(Debug, Clone, Copy)]
+pub enum Hazard {
+    /// The chest is an enemy in disguise.
+    Mimic,
+    /// Opening the chest inflicts the status effect, or damage if there's none.
+    Trap(Option<StatusEffect>),
 }
 
 impl Chest {
-    /// Randomly generate a chest at the current location.
+    /// Generate the chest at the current location, if any.
+    /// The contents are derived from the location path and the hero's world seed,
+    /// so the same directory always holds the same treasure. Its level follows the
+    /// hero's rounded level, so it only gets better every five levels.
     pub fn generate(game: &game::Game) -> Option<Self> {
+        let seed = game.location.seed() ^ game.world_seed;
+        let randomizer = seeded(seed);
+        let distance = game.location.distance_from_home();
+        if randomizer.locked_chest(&distance) {
+            return Some(Self::locked(game, &randomizer));
+        }
+
+        let mut chest = Self::roll(game, &randomizer)?;
+        chest.hazard = randomizer.chest_hazard(&distance);
+        Some(chest)
+    }
+
+    /// A chest that can only be opened with a key of the hero's level,
+    /// holding better equipment than the regular ones.
+    fn locked(game: &game::Game, randomizer: &impl Randomizer) -> Self {
+        let level = game.player.rounded_level();
+
+        Self {
+            gold: randomizer.gold_gained(level * 400),
+            lock: Some(level),
+            ..random_equipment(level + 5, randomizer)
+        }
+    }
+
+    /// Randomly generate a chest at the current location.
+    fn roll(game: &game::Game, randomizer: &impl Randomizer) -> Option<Self> {
         // To give the impression of "dynamic" chest contents, each content type
         // is randomized separately, and what's found is combined into a single
         // chest at the end
         let distance = &game.location.distance_from_home();
-        let gold_chest = random().gold_chest(distance);
-        let equipment_chest = random().equipment_chest(distance);
-        let item_chest = random().item_chest(distance);
+        let bonus = game.location.features().treasure_bonus();
+        let gold_chest = randomizer.gold_chest(distance, bonus);
+        let equipment_chest = randomizer.equipment_chest(distance, bonus);
+        let item_chest = randomizer.item_chest(distance);
 
-        let mut chest = Self::default();
+        let level = game.player.rounded_level();
 
-        if gold_chest {
-            chest.gold = random().gold_gained(game.player.level * 200)
-        }
+        let mut chest = if equipment_chest {
+            random_equipment(level, randomizer)
+        } else {
+            Self::default()
+        };
 
-        if equipment_chest {
-            let (sword, shield) = random_equipment(game.player.rounded_level());
-            chest.sword = sword;
-            chest.shield = shield;
+        if gold_chest {
+            chest.gold = randomizer.gold_gained(level * 200)
         }
 
         if item_chest {
-            chest.items = random_items(game.player.rounded_level());
+            chest.items = random_items(level, randomizer);
         }
 
         // Return None instead of an empty chest if none was found
@@ -55,44 +115,117 @@ impl Chest {
     pub fn battle_loot(game: &game::Game) -> Option<Self> {
         // reuse item % from chests, but don't add extra gold
         // kind of hacky but does for now
-        Self::generate(game).map(|mut c| {
-            c.gold = 0;
-            c
-        })
+        let mut loot: Option<Self> = None;
+
+        // the player class may get extra chances to find loot
+        for _ in 0..=game.player.class.passives.loot_rolls {
+            if let Some(mut chest) = Self::roll(game, &random()) {
+                chest.gold = 0;
+
+                // keys can only be found in battle
+                if random().key_drop(&game.location.distance_from_home()) {
+                    let key: Box<dyn Item> = Box::new(Key::new(game.player.rounded_level()));
+                    chest.items.insert(String::from("key"), vec![key]);
+                }
+
+                if let Some(previous) = loot.take() {
+                    chest.extend(previous);
+                }
+                loot = Some(chest);
+            }
+        }
+        loot
     }
 
-    /// Remove the gold, items and equipment from a hero and return them as a new chest.
-    pub fn drop(game: &mut game::Game) -> Self {
-        let sword = game.player.sword.take();
-        let shield = game.player.shield.take();
-        let items = game.inventory.drain().collect();
-        let gold = game.gold;
-        game.gold = 0;
+    /// Return the unique loot guaranteed when beating the given boss.
+    pub fn boss_loot(boss: &Boss) -> Self {
+        let sword = if boss.loot.sword {
+            Some(match boss.loot.element {
+                Some(element) => Sword::elemental(boss.level, element),
+                None => Sword::new(boss.level),
+            })
+        } else {
+            None
+        };
+        let shield = if boss.loot.shield {
+            Some(Shield::new(boss.level))
+        } else {
+            None
+        };
 
         Self {
-            items,
             sword,
             shield,
-            gold,
+            gold: boss.loot.gold,
+            ..Self::default()
         }
     }
 
-    /// Add the items of this chest to the current game/hero
-    pub fn pick_up(&mut self, game: &mut game::Game) -> (Vec<String>, i32) {
-        let mut to_log = Vec::new();
+    /// Remove the gold, items and equipment from a hero and return them as a new chest.
+    pub fn drop(game: &mut game::Game) -> Self {
+        let player = &mut game.player;
+        let chest = Self {
+            sword: player.sword.take(),
+            shield: player.shield.take(),
+            armor: player.armor.take(),
+            helmet: player.helmet.take(),
+            ring: player.ring.take(),
+            amulet: player.amulet.take(),
+            bag: game.stash.drain(..).collect(),
+            items: game.inventory.drain().collect(),
+            gold: game.gold,
+            hazard: None,
+            lock: None,
+        };
+        game.gold = 0;
+        chest
+    }
 
-        // the equipment is picked up only if it's better than the current one
-        if let Some(sword) = self.sword.take() {
-            if sword.is_upgrade_from(&game.player.sword.as_ref()) {
-                to_log.push(sword.to_string());
-                game.player.sword = Some(sword);
+    /// The minimum key level needed to open this chest, if it's locked.
+    pub fn lock(&self) -> Option<i32> {
+        self.lock
+    }
+
+    /// Remove the mimic or trap of this chest, if any, to deal with it.
+    pub fn take_hazard(&mut self) -> Option<Hazard> {
+        self.hazard.take()
+    }
+
+    /// Move a single item out of this chest into a new one,
+    /// or a piece of equipment if there are no items left.
+    pub fn take_one(&mut self) -> Option<Self> {
+        let mut taken = Self::default();
+
+        if let Some(name) = self.items.keys().next().cloned() {
+            let items = self.items.get_mut(&name).unwrap();
+            taken
+                .items
+                .insert(name.clone(), items.pop().into_iter().collect());
+            if items.is_empty() {
+                self.items.remove(&name);
             }
+            return Some(taken);
+        }
+
+        let mut equipment = self.take_equipment();
+        let gear = equipment.pop()?;
+        for left in equipment {
+            self.put(left);
         }
+        taken.put(gear);
+        Some(taken)
+    }
+
+    /// Add the items of this chest to the current game/hero
+    pub fn pick_up(&mut self, game: &mut game::Game) -> (Vec<String>, i32) {
+        let mut to_log = Vec::new();
 
-        if let Some(shield) = self.shield.take() {
-            if shield.is_upgrade_from(&game.player.shield.as_ref()) {
-                to_log.push(shield.to_string());
-                game.player.shield = Some(shield);
+        // the equipment is put on only if it's better than the current one,
+        // otherwise it's kept in the stash
+        for gear in self.take_equipment() {
+            to_log.push(gear.to_string());
+            if let Some(left_out) = game.player.equip_if_better(gear) {
+                game.stash.push(left_out);
             }
         }
 
@@ -110,19 +243,93 @@ impl Chest {
         (to_log, self.gold)
     }
 
-    /// Add the elements of `other` to this chest
-    pub fn extend(&mut self, mut other: Self) {
-        // keep the best of each equipment
-        if let Some(sword) = other.sword.take() {
-            if sword.is_upgrade_from(&self.sword.as_ref()) {
-                self.sword = Some(sword);
+    /// Describe the items and gold that would be left after
+    /// decaying to the given percentage, without picking them up.
+    pub fn contents(&self, percent: i32) -> (Vec<String>, i32) {
+        let mut contents = self.equipment();
+        for (name, items) in &self.items {
+            let count = decayed(items.len(), percent);
+            if count > 0 {
+                contents.push(format!("{}x{}", name, count));
             }
         }
+        (contents, self.gold * percent / 100)
+    }
 
-        if let Some(shield) = other.shield.take() {
-            if shield.is_upgrade_from(&self.shield.as_ref()) {
-                self.shield = Some(shield);
-            }
+    /// Reduce the gold and items to the given percentage.
+    /// Equipment is kept until the chest has fully decayed.
+    pub fn decay(&mut self, percent: i32) {
+        self.gold = self.gold * percent / 100;
+        for items in self.items.values_mut() {
+            items.truncate(decayed(items.len(), percent));
+        }
+        self.items.retain(|_, items| !items.is_empty());
+
+        if percent <= 0 {
+            self.sword = None;
+            self.shield = None;
+            self.armor = None;
+            self.helmet = None;
+            self.ring = None;
+            self.amulet = None;
+            self.bag.clear();
+        }
+    }
+
+    /// Describe the equipment in the chest.
+    fn equipment(&self) -> Vec<String> {
+        let equipment: [Option<&dyn Equipment>; 6] = [
+            self.sword.as_ref().map(|e| e as _),
+            self.shield.as_ref().map(|e| e as _),
+            self.armor.as_ref().map(|e| e as _),
+            self.helmet.as_ref().map(|e| e as _),
+            self.ring.as_ref().map(|e| e as _),
+            self.amulet.as_ref().map(|e| e as _),
+        ];
+        equipment
+            .iter()
+            .flatten()
+            .map(|e| e.to_string())
+            .chain(self.bag.iter().map(Gear::to_string))
+            .collect()
+    }
+
+    /// Remove all the equipment from the chest.
+    fn take_equipment(&mut self) -> Vec<Gear> {
+        let slots = vec![
+            self.sword.take().map(Gear::Sword),
+            self.shield.take().map(Gear::Shield),
+            self.armor.take().map(Gear::Armor),
+            self.helmet.take().map(Gear::Helmet),
+            self.ring.take().map(Gear::Ring),
+            self.amulet.take().map(Gear::Amulet),
+        ];
+        slots
+            .into_iter()
+            .flatten()
+            .chain(self.bag.drain(..))
+            .collect()
+    }
+
+    /// Put the equipment in its slot if it's better than the one there,
+    /// and the one left out in the bag.
+    fn put(&mut self, gear: Gear) {
+        let left_out = match gear {
+            Gear::Sword(sword) => keep_best(&mut self.sword, sword).map(Gear::Sword),
+            Gear::Shield(shield) => keep_best(&mut self.shield, shield).map(Gear::Shield),
+            Gear::Armor(armor) => keep_best(&mut self.armor, armor).map(Gear::Armor),
+            Gear::Helmet(helmet) => keep_best(&mut self.helmet, helmet).map(Gear::Helmet),
+            Gear::Ring(ring) => keep_best(&mut self.ring, ring).map(Gear::Ring),
+            Gear::Amulet(amulet) => keep_best(&mut self.amulet, amulet).map(Gear::Amulet),
+        };
+        self.bag.extend(left_out);
+    }
+
+    /// Add the elements of `other` to this chest
+    pub fn extend(&mut self, mut other: Self) {
+        // keep the best of each equipment, and the rest in the bag
+        for gear in other.take_equipment() {
+            self.put(gear);
         }
 
         // merge both item maps
@@ -135,21 +342,43 @@ impl Chest {
     }
 }
 
+/// Amount of items left out of `count` after decaying to the given percentage.
+fn decayed(count: usize, percent: i32) -> usize {
+    let percent = std::cmp::max(0, percent) as usize;
+    (count * percent).div_ceil(100)
+}
+
+/// A chest holding a single random piece of equipment.
 // TODO consider using weighted random instead of these matches
-fn random_equipment(level: i32) -> (Option<Sword>, Option<Shield>) {
-    match random().range(15) {
-        n if n < 8 => (Some(Sword::new(level)), None),
-        n if n < 13 => (None, Some(Shield::new(level))),
-        14 => (Some(Sword::new(level + 5)), None),
-        _ => (None, Some(Shield::new(level + 5))),
+fn random_equipment(level: i32, randomizer: &impl Randomizer) -> Chest {
+    let mut chest = Chest::default();
+    let affixes = randomizer.affixes(level);
+    match randomizer.range(27) {
+        n if n < 8 => chest.sword = Some(enchanted(Sword::new(level), affixes)),
+        n if n < 12 => chest.shield = Some(enchanted(Shield::new(level), affixes)),
+        12 => chest.sword = Some(enchanted(Sword::elemental(level, Element::Fire), affixes)),
+        13 => chest.sword = Some(enchanted(Sword::elemental(level, Element::Ice), affixes)),
+        14 => chest.sword = Some(enchanted(Sword::elemental(level, Element::Poison), affixes)),
+        15 => chest.sword = Some(enchanted(Sword::new(level + 5), affixes)),
+        n if n < 19 => chest.armor = Some(enchanted(Armor::new(level), affixes)),
+        n if n < 22 => chest.helmet = Some(enchanted(Helmet::new(level), affixes)),
+        n if n < 24 => chest.ring = Some(enchanted(Ring::new(level), affixes)),
+        n if n < 26 => chest.amulet = Some(enchanted(Amulet::new(level), affixes)),
+        _ => chest.shield = Some(enchanted(Shield::new(level + 5), affixes)),
     }
+    chest
+}
+
+fn enchanted<T: Equipment>(mut equipment: T, affixes: Vec<(Affix, i32)>) -> T {
+    equipment.set_affixes(affixes);
+    equipment
 }
 
-fn random_items(level: i32) -> HashMap<String, Vec<Box<dyn Item>>> {
+fn random_items(level: i32, randomizer: &impl Randomizer) -> HashMap<String, Vec<Box<dyn Item>>> {
     let mut map = HashMap::new();
     let potion = || Box::new(Potion::new(level));
 
-    let (key, items): (&str, Vec<Box<dyn Item>>) = match random().range(20) {
+    let (key, items): (&str, Vec<Box<dyn Item>>) = match randomizer.range(20) {
         n if n < 8 => ("potion", vec![potion()]),
         n if n < 12 => ("potion", vec![potion(), potion()]),
         n if n < 14 => ("potion", vec![potion(), potion(), potion()]),
@@ -167,7 +396,14 @@ impl Default for Chest {
             gold: 0,
             sword: None,
             shield: None,
+            armor: None,
+            helmet: None,
+            ring: None,
+            amulet: None,
+            bag: Vec::new(),
             items: HashMap::new(),
+            hazard: None,
+            lock: None,
         }
     }
 }
@@ -175,8 +411,32 @@ impl Default for Chest {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::item::equipment::{Shield, Sword};
+    use crate::item::equipment::{Armor, Shield, Sword};
     use crate::item::{Escape, Potion};
+    use crate::location::Location;
+    use crate::randomizer::DefaultRandomizer;
+
+    #[test]
+    fn test_same_chest() {
+        let mut game = game::Game::new();
+        game.location = Location::from("/").unwrap();
+        let roll = |game: &game::Game, seed| {
+            let chest = Chest::roll(game, &DefaultRandomizer::seeded(seed));
+            chest.map(|chest| chest.contents(100))
+        };
+
+        let mut found = 0;
+        for seed in 0..100 {
+            game.player.level = 5;
+            let chest = roll(&game, seed);
+            found += chest.is_some() as i32;
+
+            // the same until the hero reaches the next rounded level
+            game.player.level = 9;
+            assert_eq!(chest, roll(&game, seed));
+        }
+        assert!(found > 0);
+    }
 
     #[test]
     fn test_empty_drop_pickup() {
@@ -250,6 +510,10 @@ mod tests {
         // the shield was downgrade, kept the current one
         assert_eq!(10, game.player.shield.as_ref().unwrap().level());
 
+        // the rest of the equipment was kept in the stash
+        let stash: Vec<String> = game.stash.iter().map(Gear::to_string).collect();
+        assert_eq!(vec!["sword[1]", "shield[5]"], stash);
+
         assert_eq!(3, *game.inventory().get("potion").unwrap());
     }
 
@@ -263,6 +527,7 @@ mod tests {
             sword: Some(Sword::new(1)),
             shield: Some(Shield::new(10)),
             gold: 100,
+            ..Chest::default()
         };
 
         let potions: Vec<Box<dyn Item>> = vec![Box::new(Potion::new(1))];
@@ -274,13 +539,16 @@ mod tests {
             items,
             sword: Some(Sword::new(10)),
             shield: Some(Shield::new(1)),
+            armor: Some(Armor::new(5)),
             gold: 100,
+            ..Chest::default()
         };
 
         chest1.extend(chest2);
         assert_eq!(200, chest1.gold);
         assert_eq!(10, chest1.sword.as_ref().unwrap().level());
         assert_eq!(10, chest1.shield.as_ref().unwrap().level());
+        assert_eq!(5, chest1.armor.as_ref().unwrap().level());
         assert_eq!(3, chest1.items.get("potion").unwrap().len());
         assert_eq!(1, chest1.items.get("escape").unwrap().len());
     }
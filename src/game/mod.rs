@@ -1,20 +1,29 @@
 extern crate dirs;
 
 use crate::character;
+use crate::character::boss::Boss;
+use crate::character::ghost;
 use crate::character::Character;
+use crate::config::Config;
+use crate::datafile;
 use crate::event::Event;
+use crate::item::equipment::{Affix, Gear, MAX_AFFIXES};
+use crate::item::shop::Purchases;
 use crate::item::Item;
 use crate::location::Location;
 use crate::quest::QuestList;
 use crate::randomizer::random;
 use crate::randomizer::Randomizer;
 use anyhow::{bail, Result};
-use chest::Chest;
+use chest::{Chest, Hazard};
+use rand::seq::IteratorRandom;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
+use tombstone::Tombstone;
 
 pub mod battle;
 pub mod chest;
+pub mod tombstone;
 
 #[derive(Serialize, Deserialize)]
 #[serde(default)]
@@ -24,8 +33,58 @@ pub struct Game {
     pub gold: i32,
     pub quests: QuestList,
     pub inventory: HashMap<String, Vec<Box<dyn Item>>>,
-    pub tombstones: HashMap<String, Chest>,
-    inspected: HashSet<Location>,
+
+    /// Equipment carried by the hero but not currently in use.
+    pub stash: Vec<Gear>,
+
+    /// Crafting materials dropped by enemies, with the units of each.
+    pub materials: HashMap<String, u32>,
+    pub tombstones: HashMap<String, Tombstone>,
+    pub defeated_bosses: HashSet<String>,
+
+    /// Battles won and lost against the ghosts of each player.
+    pub ghosts: HashMap<String, ghost::Record>,
+
+    /// The last town visited, where escape items lead instead of home.
+    pub save_point: Option<Location>,
+
+    /// Units bought at the shop today, to limit its daily stock.
+    pub purchases: Purchases,
+
+    /// Determines the chests found at each location for this hero.
+    world_seed: u64,
+
+    /// Amount of battles fought by this hero.
+    battle_count: u32,
+
+    /// Locations where a chest was picked up, to know when it respawns.
+    looted: HashMap<String, Looted>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Looted {
+    /// Seconds since the unix epoch.
+    timestamp: u64,
+    battle_count: u32,
+}
+
+impl Looted {
+    fn new(battle_count: u32) -> Self {
+        Self {
+            timestamp: now(),
+            battle_count,
+        }
+    }
+
+    /// Whether enough time or battles have passed for the chest to show up again.
+    fn respawned(&self, battle_count: u32) -> bool {
+        let respawn = &Config::get().chest_respawn;
+        let elapsed_hours = now().saturating_sub(self.timestamp) / 3600;
+        let elapsed_battles = battle_count.saturating_sub(self.battle_count);
+
+        (respawn.hours > 0 && elapsed_hours >= respawn.hours)
+            || (respawn.battles > 0 && elapsed_battles >= respawn.battles)
+    }
 }
 
 impl Game {
@@ -36,8 +95,16 @@ impl Game {
             player: Character::player(),
             gold: 0,
             inventory: HashMap::new(),
+            stash: Vec::new(),
+            materials: HashMap::new(),
             tombstones: HashMap::new(),
-            inspected: HashSet::new(),
+            defeated_bosses: HashSet::new(),
+            ghosts: HashMap::new(),
+            save_point: None,
+            purchases: Purchases::default(),
+            world_seed: random().seed(),
+            battle_count: 0,
+            looted: HashMap::new(),
             quests,
         }
     }
@@ -46,9 +113,11 @@ impl Game {
     /// Progress is preserved across games.
     pub fn reset(&mut self) {
         let mut new_game = Self::new();
-        // preserve tombstones and quests across hero's lifes
+        // preserve tombstones, quests, beaten bosses and ghost records across hero's lifes
         std::mem::swap(&mut new_game.tombstones, &mut self.tombstones);
         std::mem::swap(&mut new_game.quests, &mut self.quests);
+        std::mem::swap(&mut new_game.defeated_bosses, &mut self.defeated_bosses);
+        std::mem::swap(&mut new_game.ghosts, &mut self.ghosts);
 
         // remember last selected class
         new_game
@@ -83,14 +152,184 @@ impl Game {
     }
 
     /// Look for chests and tombstones at the current location.
-    /// Remembers previously visited locations for consistency.
-    pub fn inspect(&mut self) {
-        let maybe_tomb = self.tombstones.remove(&self.location.to_string());
-        self.pick_up_chest(maybe_tomb, true);
+    /// Chests are the same on every visit, so only the looted
+    /// locations need to be remembered until the chest respawns.
+    /// Chests can also be mimics or be trapped, which may kill the hero.
+    pub fn inspect(&mut self) -> Result<(), character::Dead> {
+        let location = self.location.to_string();
+        let maybe_tomb = self.tombstones.remove(&location);
+        let mut maybe_chest = maybe_tomb.map(Tombstone::into_chest);
+
+        // other heroes may have fallen here, too
+        for tombstone in self.dig_up_shared() {
+            let mut chest = tombstone.into_chest();
+            if let Some(previous) = maybe_chest.take() {
+                chest.extend(previous);
+            }
+            maybe_chest = Some(chest);
+        }
+        self.pick_up_chest(maybe_chest, true);
 
-        if !self.inspected.contains(&self.location) {
-            self.inspected.insert(self.location.clone());
-            self.pick_up_chest(Chest::generate(self), false);
+        if self.chest_respawned(&location) {
+            if let Some(mut chest) = Chest::generate(self) {
+                if let Some(level) = chest.lock() {
+                    Event::emit(self, Event::ChestLocked { level });
+                    return Ok(());
+                }
+                if let Some(hazard) = chest.take_hazard() {
+                    if !self.face_hazard(hazard)? {
+                        return Ok(());
+                    }
+                }
+                self.mark_looted(location);
+                self.pick_up_chest(Some(chest), false);
+            }
+        }
+        Ok(())
+    }
+
+    /// Open the locked chest at the current location, if any, provided
+    /// the key level is enough. Return whether the chest was opened.
+    pub fn unlock_chest(&mut self, key_level: i32) -> bool {
+        let location = self.location.to_string();
+        if !self.chest_respawned(&location) {
+            return false;
+        }
+
+        let maybe_chest = Chest::generate(self);
+        match maybe_chest.as_ref().and_then(Chest::lock) {
+            Some(level) if level > key_level => {
+                Event::emit(self, Event::ChestLocked { level });
+                false
+            }
+            Some(_) => {
+                self.mark_looted(location);
+                self.pick_up_chest(maybe_chest, false);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Whether there's a locked chest at the current location
+    /// that a key of the given level can open.
+    pub fn can_unlock(&self, key_level: i32) -> bool {
+        let location = self.location.to_string();
+        if !self.chest_respawned(&location) {
+            return false;
+        }
+
+        let lock = Chest::generate(self).as_ref().and_then(Chest::lock);
+        matches!(lock, Some(level) if level <= key_level)
+    }
+
+    /// Remember that the chest at the given location was picked up,
+    /// forgetting the locations where chests already respawned.
+    fn mark_looted(&mut self, location: String) {
+        let battle_count = self.battle_count;
+        self.looted
+            .retain(|_, looted| !looted.respawned(battle_count));
+        self.looted.insert(location, Looted::new(battle_count));
+    }
+
+    fn chest_respawned(&self, location: &str) -> bool {
+        match self.looted.get(location) {
+            Some(looted) => looted.respawned(self.battle_count),
+            None => true,
+        }
+    }
+
+    /// Leave the tombstone at the current location, in the shared graveyard
+    /// if there's one configured, or in the game data otherwise.
+    fn bury(&mut self, tombstone: Tombstone) {
+        if let Some(dir) = &Config::get().shared_graveyard {
+            let location = self.location.path_string();
+            let shared = datafile::update_shared_graveyard(dir, |graveyard| {
+                let tombstones = graveyard.entry(tombstone.owner.clone()).or_default();
+                tombstone::bury(tombstones, location, tombstone.clone());
+            });
+            if shared.is_ok() {
+                return;
+            }
+        }
+
+        // keep it locally if the shared graveyard is not available
+        let location = self.location.to_string();
+        tombstone::bury(&mut self.tombstones, location, tombstone);
+    }
+
+    /// Remove the tombstones of any hero at the current location
+    /// from the shared graveyard, if there's one configured.
+    fn dig_up_shared(&self) -> Vec<Tombstone> {
+        let dir = match &Config::get().shared_graveyard {
+            Some(dir) => dir,
+            None => return Vec::new(),
+        };
+
+        // only lock and rewrite the file if there's something to dig up
+        let location = self.location.path_string();
+        let found = datafile::load_shared_graveyard(dir)
+            .map(|graveyard| {
+                graveyard
+                    .values()
+                    .any(|tombs| tombs.contains_key(&location))
+            })
+            .unwrap_or_default();
+        if !found {
+            return Vec::new();
+        }
+
+        let result = datafile::update_shared_graveyard(dir, |graveyard| {
+            graveyard
+                .values_mut()
+                .filter_map(|tombstones| tombstones.remove(&location))
+                .collect()
+        });
+        result.unwrap_or_default()
+    }
+
+    /// Return the tombstones of the fallen heroes that haven't fully decayed,
+    /// removing the expired ones.
+    pub fn graveyard(&mut self) -> Vec<(&String, &Tombstone)> {
+        self.tombstones
+            .retain(|_, tombstone| !tombstone.is_expired());
+        let mut tombstones: Vec<_> = self.tombstones.iter().collect();
+        tombstones.sort_by_key(|(_, tombstone)| std::cmp::Reverse(tombstone.timestamp));
+        tombstones
+    }
+
+    /// Deal with a mimic or a trapped chest, and return whether
+    /// the chest can be opened afterwards.
+    fn face_hazard(&mut self, hazard: Hazard) -> Result<bool, character::Dead> {
+        let detected = random().detects_hazard(self.player.class.passives.trap_detection);
+
+        match hazard {
+            Hazard::Mimic if detected => {
+                // leave it alone
+                Event::emit(self, Event::MimicDetected);
+                Ok(false)
+            }
+            Hazard::Mimic => {
+                let mut enemy = character::enemy::mimic(&self.location, &self.player);
+                Event::emit(self, Event::EnemyAppears { enemy: &enemy });
+                self.maybe_battle(&mut enemy, false, false)?;
+                Ok(true)
+            }
+            Hazard::Trap(_) if detected => {
+                Event::emit(self, Event::TrapDisarmed);
+                Ok(true)
+            }
+            Hazard::Trap(status) => {
+                let damage = if let Some(status) = status {
+                    self.player.set_status_effect(status);
+                    0
+                } else {
+                    self.player.effective_max_hp() / 5
+                };
+                let result = self.player.receive_damage(damage);
+                Event::emit(self, Event::TrapTriggered { damage, status });
+                result.map(|_| true)
+            }
         }
     }
 
@@ -112,28 +351,28 @@ impl Game {
     pub fn visit(&mut self, location: Location) -> Result<(), character::Dead> {
         self.location = location;
         if self.location.is_home() {
-            let (recovered_hp, recovered_mp) = self.player.heal_full();
-            let healed = self.player.maybe_remove_status_effect();
-            Event::emit(
-                self,
-                Event::Heal {
-                    item: None,
-                    recovered_hp,
-                    recovered_mp,
-                    healed,
-                },
-            );
+            self.save_point = None;
+            self.rest();
+        } else if self.location.is_town() {
+            self.save_point = Some(self.location.clone());
         }
 
+        self.player.regen_mp();
+
         // In location is home, already healed of negative status
-        self.maybe_receive_status_damage()
+        self.maybe_receive_status_damage()?;
+        self.player.countdown_status_effect(false);
+        Ok(())
     }
 
-    /// Player takes damage from status_effects, if any.
+    /// Player takes damage from (or gets healed by) status_effects, if any.
     fn maybe_receive_status_damage(&mut self) -> Result<(), character::Dead> {
         if let Some(damage) = self.player.receive_status_effect_damage()? {
             Event::emit(self, Event::StatusEffectDamage { damage });
         }
+        if let Some(recovered_hp) = self.player.receive_status_effect_heal() {
+            Event::emit(self, Event::StatusEffectHeal { recovered_hp });
+        }
         Ok(())
     }
 
@@ -142,6 +381,52 @@ impl Game {
         self.visit(Location::home()).unwrap_or_default();
     }
 
+    /// Go back to the last town visited or, if it's no longer a town or
+    /// the hero has been home since, to home.
+    pub fn visit_save_point(&mut self) {
+        match self.save_point.take().filter(|town| town.is_town()) {
+            Some(town) => self.visit(town).unwrap_or_default(),
+            None => self.visit_home(),
+        }
+    }
+
+    /// Pay to rest at the inn of the current town, fully restoring the hero.
+    pub fn rest_at_inn(&mut self) -> Result<()> {
+        if !self.location.is_town() {
+            bail!("Inn is only available in towns.");
+        }
+
+        if self.player.current_hp == self.player.effective_max_hp()
+            && self.player.current_mp == self.player.effective_max_mp()
+            && self.player.status_effect.is_none()
+        {
+            bail!("The hero is already rested.");
+        }
+
+        let cost = Config::get().towns.inn_cost * self.player.level;
+        if self.gold < cost {
+            bail!("Not enough gold.");
+        }
+        self.gold -= cost;
+        self.rest();
+        Ok(())
+    }
+
+    /// Restore the hero's hp and mp and remove any status effect.
+    fn rest(&mut self) {
+        let (recovered_hp, recovered_mp) = self.player.heal_full();
+        let healed = self.player.maybe_remove_status_effect();
+        Event::emit(
+            self,
+            Event::Heal {
+                item: None,
+                recovered_hp,
+                recovered_mp,
+                healed,
+            },
+        );
+    }
+
     // TODO consider introducing an item "bag" wrapper over these types of hashmaps
     // (same is used in chests and in tests)
     pub fn add_item(&mut self, name: &str, item: Box<dyn Item>) {
@@ -154,6 +439,14 @@ impl Game {
 
     pub fn use_item(&mut self, name: &str) -> Result<()> {
         let name = name.to_string();
+        let usable = match self.inventory.get(&name).and_then(|items| items.last()) {
+            Some(item) => item.is_usable(self),
+            None => bail!("Item not found."),
+        };
+        if !usable {
+            bail!("The {} can't be used here.", name);
+        }
+
         // get all items of that type and use one
         // if there are no remaining, drop the type from the inventory
         if let Some(mut items) = self.inventory.remove(&name) {
@@ -172,6 +465,109 @@ impl Game {
         }
     }
 
+    /// Put on the equipment of the stash with the given description,
+    /// or the best one for the slot with the given name.
+    /// The one previously equipped is kept in the stash.
+    pub fn equip(&mut self, name: &str) -> Result<()> {
+        let index = self
+            .stash_matches(name)
+            .into_iter()
+            .max_by_key(|(_, gear)| gear.equipment().value())
+            .map(|(index, _)| index);
+
+        if let Some(index) = index {
+            let gear = self.stash.remove(index);
+            self.wear(gear);
+            Ok(())
+        } else {
+            bail!("Equipment not found.")
+        }
+    }
+
+    /// The positions of the equipment in the stash with the given description
+    /// or, if there's none, of the equipment for the slot with the given name.
+    pub fn stash_matches(&self, name: &str) -> Vec<(usize, &Gear)> {
+        let exact: Vec<_> = self
+            .stash
+            .iter()
+            .enumerate()
+            .filter(|(_, gear)| gear.to_string() == name)
+            .collect();
+        if !exact.is_empty() {
+            return exact;
+        }
+
+        self.stash
+            .iter()
+            .enumerate()
+            .filter(|(_, gear)| gear.slot() == name)
+            .collect()
+    }
+
+    /// Determines the shop stock of the given day for this hero.
+    pub fn shop_seed(&self, day: u64) -> u64 {
+        self.world_seed ^ day
+    }
+
+    /// Add a bonus to the equipment worn in the given slot.
+    pub fn add_affix(&mut self, slot: &str, affix: Affix, value: i32) -> Result<()> {
+        let equipment = match self.player.equipment_mut(slot) {
+            Some(equipment) => equipment,
+            None => bail!("Nothing equipped in that slot."),
+        };
+
+        let mut affixes = equipment.affixes().to_vec();
+        if affixes.iter().any(|(kind, _)| *kind == affix) {
+            bail!("The equipment already has that bonus.");
+        } else if affixes.len() >= MAX_AFFIXES {
+            bail!("The equipment can't hold more bonuses.");
+        }
+        affixes.push((affix, value));
+        equipment.set_affixes(affixes);
+        Ok(())
+    }
+
+    /// Raise the level of the equipment worn in the given slot.
+    pub fn add_level(&mut self, slot: &str) -> Result<()> {
+        let equipment = match self.player.equipment_mut(slot) {
+            Some(equipment) => equipment,
+            None => bail!("Nothing equipped in that slot."),
+        };
+        equipment.set_level(equipment.level() + 1);
+        Ok(())
+    }
+
+    pub fn add_material(&mut self, name: &str) {
+        *self.materials.entry(name.to_string()).or_default() += 1;
+    }
+
+    /// Consume the given units of a material, dropping it once there are no more.
+    pub fn remove_material(&mut self, name: &str, units: u32) {
+        if let Some(count) = self.materials.get_mut(name) {
+            *count = count.saturating_sub(units);
+            if *count == 0 {
+                self.materials.remove(name);
+            }
+        }
+    }
+
+    /// Take off the equipment of the given slot and keep it in the stash.
+    pub fn unequip(&mut self, slot: &str) -> Result<()> {
+        if let Some(gear) = self.player.unequip(slot) {
+            self.stash.push(gear);
+            Ok(())
+        } else {
+            bail!("Nothing equipped in that slot.")
+        }
+    }
+
+    /// Put on the given equipment, keeping the previous one in the stash.
+    pub fn wear(&mut self, gear: Gear) {
+        if let Some(previous) = self.player.equip(gear) {
+            self.stash.push(previous);
+        }
+    }
+
     pub fn inventory(&self) -> HashMap<&str, usize> {
         self.inventory
             .iter()
@@ -190,16 +586,42 @@ impl Game {
         }
     }
 
+    /// Return the boss guarding the current location, unless it was already beaten.
+    pub fn boss_at_location(&self) -> Option<&'static Boss> {
+        Boss::at(&self.location).filter(|boss| !self.defeated_bosses.contains(boss.name()))
+    }
+
     pub fn maybe_spawn_enemy(&mut self) -> Option<Character> {
         let distance = self.location.distance_from_home();
-        if random().should_enemy_appear(&distance) {
-            let enemy = character::enemy::at(&self.location, &self.player);
-
-            Event::emit(self, Event::EnemyAppears { enemy: &enemy });
-            Some(enemy)
+        let features = self.location.features();
+
+        // bosses are always there until beaten
+        let enemy = if let Some(boss) = self.boss_at_location() {
+            boss.character()
+        } else if random().should_enemy_appear(&distance, features.enemy_bonus()) {
+            self.maybe_find_ghost()
+                .unwrap_or_else(|| character::enemy::at(&self.location, features, &self.player))
         } else {
-            None
+            return None;
+        };
+
+        Event::emit(self, Event::EnemyAppears { enemy: &enemy });
+        Some(enemy)
+    }
+
+    /// Pick the ghost of a hero exported by another player, if there's
+    /// a ghosts directory configured and any of them is a match for the hero.
+    fn maybe_find_ghost(&self) -> Option<Character> {
+        let dir = Config::get().ghosts.as_ref()?;
+        if !ghost::should_appear() {
+            return None;
         }
+
+        datafile::load_ghosts(dir)
+            .into_iter()
+            .filter(|(_, hero)| ghost::is_match(hero, &self.player))
+            .choose(&mut rand::thread_rng())
+            .map(|(owner, hero)| ghost::enemy(&owner, hero))
     }
 
     pub fn maybe_battle(
@@ -223,7 +645,8 @@ impl Game {
     fn bribe(&mut self, enemy: &Character) -> bool {
         let bribe_cost = gold_gained(self.player.level, enemy.level) / 2;
 
-        if self.gold >= bribe_cost && random().bribe_succeeds() {
+        let bonus = self.player.class.passives.escape;
+        if self.gold >= bribe_cost && random().bribe_succeeds(bonus) {
             self.gold -= bribe_cost;
             Event::emit(self, Event::Bribe { cost: bribe_cost });
             return true;
@@ -236,22 +659,48 @@ impl Game {
         let success = random().run_away_succeeds(
             self.player.level,
             enemy.level,
-            self.player.speed,
-            enemy.speed,
+            self.player.effective_speed(),
+            enemy.effective_speed(),
+            self.player.class.passives.escape,
         );
         Event::emit(self, Event::RunAway { success });
         success
     }
 
     fn battle(&mut self, enemy: &mut Character) -> Result<(), character::Dead> {
-        match battle::run(self, enemy, &random()) {
+        self.battle_count += 1;
+
+        // rolled beforehand so the enemy can be stolen from during the battle
+        let mut loot = Chest::battle_loot(self);
+        match battle::run(self, enemy, &mut loot, &random()) {
             Ok(xp) => {
                 let gold = gold_gained(self.player.level, enemy.level);
                 self.gold += gold;
+                if let Some(owner) = ghost::owner(enemy) {
+                    self.ghosts.entry(owner.to_string()).or_default().wins += 1;
+                }
                 let levels_up = self.player.add_experience(xp);
 
-                let reward_items =
-                    Chest::battle_loot(self).map_or(Vec::new(), |mut chest| chest.pick_up(self).0);
+                if let Some(boss) = self.boss_at_location() {
+                    if boss.name() == enemy.name() {
+                        self.defeated_bosses.insert(boss.name().to_string());
+                        let mut boss_loot = Chest::boss_loot(boss);
+                        if let Some(previous) = loot.take() {
+                            boss_loot.extend(previous);
+                        }
+                        loot = Some(boss_loot);
+                    }
+                }
+                let (mut reward_items, loot_gold) =
+                    loot.map_or((Vec::new(), 0), |mut chest| chest.pick_up(self));
+                if random().material_drop() {
+                    if let Some(material) = enemy.class.drops.iter().choose(&mut rand::thread_rng())
+                    {
+                        self.add_material(material);
+                        reward_items.push(material.clone());
+                    }
+                }
+                let gold = gold + loot_gold;
 
                 Event::emit(
                     self,
@@ -278,14 +727,14 @@ impl Game {
                 Ok(())
             }
             Err(character::Dead) => {
+                if let Some(owner) = ghost::owner(enemy) {
+                    self.ghosts.entry(owner.to_string()).or_default().losses += 1;
+                }
+
                 // Drop hero items in the location. If there was a previous tombstone
                 // merge the contents of both chests
-                let mut tombstone = Chest::drop(self);
-                let location = self.location.to_string();
-                if let Some(previous) = self.tombstones.remove(&location) {
-                    tombstone.extend(previous);
-                }
-                self.tombstones.insert(location, tombstone);
+                let tombstone = Tombstone::new(self);
+                self.bury(tombstone);
 
                 Event::emit(self, Event::BattleLost);
                 Err(character::Dead)
@@ -300,6 +749,18 @@ impl Default for Game {
     }
 }
 
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// Days since the unix epoch.
+pub fn today() -> u64 {
+    now() / (24 * 60 * 60)
+}
+
 fn gold_gained(player_level: i32, enemy_level: i32) -> i32 {
     let level = std::cmp::max(1, enemy_level - player_level);
     random().gold_gained(level * 50)
@@ -308,7 +769,9 @@ fn gold_gained(player_level: i32, enemy_level: i32) -> i32 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::character::Element;
     use crate::item;
+    use crate::item::equipment::{Armor, Equipment, Sword};
 
     #[test]
     fn test_inventory() {
@@ -342,4 +805,150 @@ mod tests {
         assert_eq!(0, game.inventory().len());
         assert!(game.use_item("potion").is_err());
     }
+
+    #[test]
+    fn test_equip() {
+        let mut game = Game::new();
+        game.player.sword = Some(Sword::new(5));
+        game.stash
+            .push(Gear::Sword(Sword::elemental(1, Element::Poison)));
+        game.stash.push(Gear::Sword(Sword::new(10)));
+        game.stash.push(Gear::Armor(Armor::new(1)));
+
+        // by description
+        assert!(game.equip("sword[1]+poison").is_ok());
+        assert_eq!(
+            "sword[1]+poison",
+            game.player.sword.as_ref().unwrap().to_string()
+        );
+        assert_eq!(3, game.stash.len());
+
+        // the best one for the slot
+        assert!(game.equip("sword").is_ok());
+        assert_eq!(10, game.player.sword.as_ref().unwrap().level());
+        assert!(game.equip("helmet").is_err());
+
+        // removing the armor takes away its hp
+        assert!(game.equip("armor").is_ok());
+        game.player.heal_full();
+        assert!(game.player.current_hp > game.player.max_hp);
+        assert!(game.unequip("armor").is_ok());
+        assert!(game.player.armor.is_none());
+        assert_eq!(game.player.max_hp, game.player.current_hp);
+        assert!(game.unequip("armor").is_err());
+        assert_eq!(3, game.stash.len());
+    }
+
+    #[test]
+    fn test_buff() {
+        let mut game = Game::new();
+        game.add_item("herb", Box::new(item::Buff::herb()));
+        game.add_item("coffee", Box::new(item::Buff::coffee()));
+
+        // negative status effects are not cured by buffs
+        let poison = character::StatusEffect::Poison;
+        game.player.set_status_effect(poison);
+        assert!(game.use_item("herb").is_err());
+        assert_eq!(Some(poison), game.player.status_effect);
+        assert_eq!(1, *game.inventory().get("herb").unwrap());
+
+        game.player.maybe_remove_status_effect();
+        assert!(game.use_item("herb").is_ok());
+        let regen = character::StatusEffect::Regen;
+        assert_eq!(Some(regen), game.player.status_effect);
+
+        // but they replace positive ones
+        assert!(game.use_item("coffee").is_ok());
+        let haste = character::StatusEffect::Haste;
+        assert_eq!(Some(haste), game.player.status_effect);
+    }
+
+    #[test]
+    fn test_unused_key() {
+        let mut game = Game::new();
+        game.add_item("key", Box::new(item::Key::new(1)));
+
+        game.add_item("key", Box::new(item::Key::new(1)));
+
+        // there's no locked chest, so the keys are kept
+        assert!(!game.can_unlock(1));
+        assert!(!game.unlock_chest(1));
+        assert!(game.use_item("key").is_err());
+        assert_eq!(2, *game.inventory().get("key").unwrap());
+    }
+
+    #[test]
+    fn test_chest_trap() {
+        let mut game = Game::new();
+        let max_hp = game.player.max_hp;
+
+        assert!(matches!(game.face_hazard(Hazard::Trap(None)), Ok(true)));
+        assert_eq!(max_hp - max_hp / 5, game.player.current_hp);
+        assert!(game.player.status_effect.is_none());
+
+        let poison = character::StatusEffect::Poison;
+        assert!(matches!(
+            game.face_hazard(Hazard::Trap(Some(poison))),
+            Ok(true)
+        ));
+        assert_eq!(max_hp - max_hp / 5, game.player.current_hp);
+        assert_eq!(Some(poison), game.player.status_effect);
+
+        game.player.current_hp = 1;
+        assert!(game.face_hazard(Hazard::Trap(None)).is_err());
+    }
+
+    #[test]
+    fn test_chest_respawn() {
+        let looted = Looted::new(10);
+        assert!(!looted.respawned(10));
+        assert!(!looted.respawned(34));
+        assert!(looted.respawned(35));
+
+        let looted = Looted {
+            timestamp: now() - 25 * 3600,
+            battle_count: 10,
+        };
+        assert!(looted.respawned(10));
+
+        // the respawned ones are forgotten
+        let mut game = Game::new();
+        game.mark_looted(String::from("/a"));
+        game.battle_count = 25;
+        game.mark_looted(String::from("/b"));
+        assert!(!game.looted.contains_key("/a"));
+        assert!(game.looted.contains_key("/b"));
+    }
+
+    #[test]
+    fn test_town() {
+        let dir = std::env::temp_dir().join("rpg-town-test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(".rpg-town"), "").unwrap();
+        let town = Location::from(dir.to_str().unwrap()).unwrap();
+        assert!(town.is_town());
+
+        let mut game = Game::new();
+        assert!(game.visit(town.clone()).is_ok());
+        assert_eq!(Some(town.clone()), game.save_point);
+
+        // resting at the inn costs gold
+        game.player.current_hp = 1;
+        assert!(game.rest_at_inn().is_err());
+        game.gold = 100;
+        assert!(game.rest_at_inn().is_ok());
+        assert_eq!(75, game.gold);
+        assert_eq!(game.player.max_hp, game.player.current_hp);
+
+        // escaping leads back to the town
+        assert!(game.visit(Location::from("/").unwrap()).is_ok());
+        assert!(game.rest_at_inn().is_err());
+        game.visit_save_point();
+        assert_eq!(town, game.location);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        game.visit_save_point();
+        assert!(game.location.is_home());
+    }
 }
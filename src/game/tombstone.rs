@@ -0,0 +1,214 @@
+use super::chest::Chest;
+use crate::config::Config;
+use crate::datafile;
+use crate::game::Game;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// The remains of a fallen hero, holding what they carried when they died.
+/// The contents decay as time goes by and as other heroes die.
+#[derive(Serialize, Deserialize)]
+pub struct Tombstone {
+    #[serde(flatten)]
+    chest: Chest,
+
+    /// Name of the user that played the fallen hero.
+    #[serde(default)]
+    pub owner: String,
+
+    #[serde(default)]
+    pub class: String,
+
+    #[serde(default)]
+    pub level: i32,
+
+    /// Seconds since the unix epoch.
+    #[serde(default = "super::now")]
+    pub timestamp: u64,
+
+    /// Amount of heroes that died after this one.
+    #[serde(default)]
+    pub later_deaths: u32,
+}
+
+/// The chest holds items that can only be copied through their serialized form.
+impl Clone for Tombstone {
+    fn clone(&self) -> Self {
+        serde_json::from_slice(&serde_json::to_vec(self).unwrap()).unwrap()
+    }
+}
+
+impl Tombstone {
+    /// Remove the gold, items and equipment from the hero and bury them.
+    pub fn new(game: &mut Game) -> Self {
+        Self {
+            owner: datafile::username(),
+            class: game.player.name(),
+            level: game.player.level,
+            timestamp: super::now(),
+            later_deaths: 0,
+            chest: Chest::drop(game),
+        }
+    }
+
+    /// Percentage of the original contents that hasn't decayed yet.
+    pub fn remaining(&self) -> i32 {
+        let decay = &Config::get().tombstone_decay;
+
+        let hours = super::now().saturating_sub(self.timestamp) / 3600;
+        let by_time = (hours * 100).checked_div(decay.days * 24).unwrap_or(0);
+        let by_deaths = (self.later_deaths * 100)
+            .checked_div(decay.deaths)
+            .unwrap_or(0);
+
+        let decayed = std::cmp::max(by_time as i32, by_deaths as i32);
+        std::cmp::max(0, 100 - decayed)
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    /// The descriptions of the remaining items and the gold, for display.
+    pub fn contents(&self) -> (Vec<String>, i32) {
+        self.chest.contents(self.remaining())
+    }
+
+    /// Merge the remains of an older tombstone into this one.
+    pub fn extend(&mut self, other: Self) {
+        self.chest.extend(other.into_chest());
+    }
+
+    /// Return what's left of the buried contents.
+    pub fn into_chest(mut self) -> Chest {
+        let remaining = self.remaining();
+        self.chest.decay(remaining);
+        self.chest
+    }
+}
+
+/// The tombstones of each user, by location.
+pub type SharedGraveyard = HashMap<String, HashMap<String, Tombstone>>;
+
+/// Add a tombstone to the given ones, by location. If there was a previous
+/// tombstone at the same location, merge their contents. The rest of the
+/// tombstones decay as a new hero died, and the expired ones are removed.
+pub fn bury(
+    tombstones: &mut HashMap<String, Tombstone>,
+    location: String,
+    mut tombstone: Tombstone,
+) {
+    if let Some(previous) = tombstones.remove(&location) {
+        tombstone.extend(previous);
+    }
+
+    for previous in tombstones.values_mut() {
+        previous.later_deaths += 1;
+    }
+    tombstones.retain(|_, tombstone| !tombstone.is_expired());
+    tombstones.insert(location, tombstone);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::Potion;
+
+    #[test]
+    fn test_decay() {
+        let mut game = Game::new();
+        game.gold = 100;
+        for _ in 0..4 {
+            game.add_item("potion", Box::new(Potion::new(1)));
+        }
+        let mut tombstone = Tombstone::new(&mut game);
+        assert_eq!(100, tombstone.remaining());
+        assert_eq!((vec![String::from("potionx4")], 100), tombstone.contents());
+
+        // decays a third with each later death
+        tombstone.later_deaths = 1;
+        assert_eq!(67, tombstone.remaining());
+        assert_eq!((vec![String::from("potionx3")], 67), tombstone.contents());
+
+        tombstone.later_deaths = 3;
+        assert!(tombstone.is_expired());
+
+        // and a seventh each day
+        tombstone.later_deaths = 0;
+        tombstone.timestamp -= 24 * 3600;
+        assert_eq!(86, tombstone.remaining());
+
+        let chest = tombstone.into_chest();
+        assert_eq!((vec![String::from("potionx4")], 86), chest.contents(100));
+    }
+
+    #[test]
+    fn test_bury() {
+        let mut tombstones = HashMap::new();
+
+        let mut game = Game::new();
+        game.gold = 90;
+        bury(
+            &mut tombstones,
+            String::from("/a"),
+            Tombstone::new(&mut game),
+        );
+
+        game.gold = 10;
+        bury(
+            &mut tombstones,
+            String::from("/b"),
+            Tombstone::new(&mut game),
+        );
+        assert_eq!(1, tombstones.get("/a").unwrap().later_deaths);
+        assert_eq!(0, tombstones.get("/b").unwrap().later_deaths);
+
+        // merged with the decayed one at the same location
+        game.gold = 10;
+        bury(
+            &mut tombstones,
+            String::from("/a"),
+            Tombstone::new(&mut game),
+        );
+        assert_eq!(2, tombstones.len());
+        assert_eq!(70, tombstones.get("/a").unwrap().contents().1);
+        assert_eq!(1, tombstones.get("/b").unwrap().later_deaths);
+
+        // expired tombstones are removed
+        tombstones.get_mut("/b").unwrap().later_deaths = 2;
+        game.gold = 10;
+        bury(
+            &mut tombstones,
+            String::from("/c"),
+            Tombstone::new(&mut game),
+        );
+        assert_eq!(2, tombstones.len());
+        assert!(!tombstones.contains_key("/b"));
+    }
+
+    #[test]
+    fn test_deserialize_chest() {
+        // tombstones used to be saved as plain chests
+        let data = r#"{"items":{"potion":[{"type":"Potion","level":1}]},"sword":null,"shield":null,"gold":10}"#;
+        let tombstone: Tombstone = serde_json::from_str(data).unwrap();
+        assert_eq!(100, tombstone.remaining());
+        assert_eq!((vec![String::from("potionx1")], 10), tombstone.contents());
+        assert_eq!(tombstone.contents(), tombstone.clone().contents());
+    }
+
+    #[test]
+    fn test_corrupt_shared_graveyard() {
+        let dir = std::env::temp_dir().join(format!("rpg-graveyard-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        assert!(datafile::load_shared_graveyard(&dir).unwrap().is_empty());
+
+        // a file that can't be parsed is left untouched
+        let file = dir.join("graveyard");
+        std::fs::write(&file, "{\"alice\":").unwrap();
+        assert!(datafile::load_shared_graveyard(&dir).is_err());
+        assert!(datafile::update_shared_graveyard(&dir, |_| ()).is_err());
+        assert_eq!("{\"alice\":", std::fs::read_to_string(&file).unwrap());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,184 @@
+use super::equipment::Affix;
+use crate::event::Event;
+use crate::game::Game;
+use crate::item::{Escape, Ether, Item, Potion, Remedy};
+use crate::log;
+use anyhow::{bail, Result};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A way to turn the materials dropped by enemies into an item
+/// or a bonus for a piece of equipment.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Recipe {
+    pub name: String,
+
+    /// The materials consumed by the recipe, with the units of each.
+    pub materials: HashMap<String, u32>,
+
+    pub product: Product,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Product {
+    Potion,
+    Ether,
+    Remedy,
+    Escape,
+
+    /// A bonus added to the equipment worn in the given slot.
+    Affix {
+        slot: String,
+        kind: Affix,
+        value: i32,
+    },
+}
+
+static RECIPES: OnceCell<Vec<Recipe>> = OnceCell::new();
+
+impl Recipe {
+    /// Customize the recipe definitions based on an input yaml byte array.
+    pub fn load(bytes: &[u8]) {
+        RECIPES.set(from_bytes(bytes)).unwrap();
+    }
+
+    pub fn all() -> &'static [Recipe] {
+        RECIPES.get_or_init(default_recipes)
+    }
+
+    pub fn find(name: &str) -> Option<&'static Self> {
+        Self::all().iter().find(|recipe| recipe.name == name)
+    }
+
+    /// Whether the hero carries all the materials needed for the recipe.
+    pub fn is_available(&self, game: &Game) -> bool {
+        self.materials
+            .iter()
+            .all(|(name, units)| game.materials.get(name).unwrap_or(&0) >= units)
+    }
+
+    /// Add the product of the recipe to the game, failing if it can't be used.
+    fn produce(&self, game: &mut Game) -> Result<()> {
+        let level = game.player.rounded_level();
+        let (name, item): (&str, Box<dyn Item>) = match &self.product {
+            Product::Potion => ("potion", Box::new(Potion::new(level))),
+            Product::Ether => ("ether", Box::new(Ether::new(level))),
+            Product::Remedy => ("remedy", Box::new(Remedy::new())),
+            Product::Escape => ("escape", Box::new(Escape::new())),
+            Product::Affix { slot, kind, value } => {
+                return game.add_affix(slot, *kind, *value);
+            }
+        };
+        game.add_item(name, item);
+        Ok(())
+    }
+}
+
+/// Print the recipes, the materials they need and the ones the hero carries.
+pub fn list(game: &Game) -> Result<()> {
+    if !game.location.is_home() {
+        bail!("Crafting is only allowed at home.");
+    }
+
+    log::recipe_list(game, Recipe::all());
+    Ok(())
+}
+
+/// Consume the materials of the given recipe to craft its product.
+pub fn craft(game: &mut Game, name: &str) -> Result<()> {
+    if !game.location.is_home() {
+        bail!("Crafting is only allowed at home.");
+    }
+
+    let recipe = match Recipe::find(name) {
+        Some(recipe) => recipe,
+        None => bail!("Unknown recipe."),
+    };
+    if !recipe.is_available(game) {
+        bail!("Not enough materials.");
+    }
+
+    recipe.produce(game)?;
+    for (material, units) in &recipe.materials {
+        game.remove_material(material, *units);
+    }
+    Event::emit(
+        game,
+        Event::ItemCrafted {
+            recipe: recipe.name.clone(),
+        },
+    );
+    Ok(())
+}
+
+fn default_recipes() -> Vec<Recipe> {
+    from_bytes(include_bytes!("recipes.yaml"))
+}
+
+fn from_bytes(bytes: &[u8]) -> Vec<Recipe> {
+    serde_yaml::from_slice(bytes).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::character::class::{Category, Class};
+    use crate::item::equipment::{Equipment, Sword};
+
+    #[test]
+    fn test_craft() {
+        let mut game = Game::new();
+        assert!(craft(&mut game, "potion").is_err());
+
+        game.add_material("slime gel");
+        game.add_material("slime gel");
+        game.add_material("slime gel");
+        assert!(craft(&mut game, "unknown").is_err());
+        assert!(craft(&mut game, "potion").is_ok());
+        assert_eq!(1, *game.inventory().get("potion").unwrap());
+        assert_eq!(Some(&1), game.materials.get("slime gel"));
+        assert!(craft(&mut game, "potion").is_err());
+    }
+
+    #[test]
+    fn test_craft_affix() {
+        let mut game = Game::new();
+        for _ in 0..3 {
+            game.add_material("snake fang");
+            game.add_material("spider silk");
+        }
+
+        // materials are kept if there's no equipment to improve
+        assert!(craft(&mut game, "venom edge").is_err());
+        assert_eq!(Some(&3), game.materials.get("snake fang"));
+
+        game.player.sword = Some(Sword::new(1));
+        assert!(craft(&mut game, "venom edge").is_ok());
+        assert_eq!(10, game.player.affix_bonus(Affix::Poison));
+        assert!(!game.materials.contains_key("snake fang"));
+        assert_eq!(Some(&1), game.materials.get("spider silk"));
+        assert_eq!(1, game.player.sword.as_ref().unwrap().affixes().len());
+    }
+
+    #[test]
+    fn test_default_recipes() {
+        for recipe in Recipe::all() {
+            assert!(!recipe.materials.is_empty());
+        }
+        assert_eq!(Product::Potion, Recipe::find("potion").unwrap().product);
+
+        // every material dropped by enemies is good for something
+        let used: Vec<&String> = Recipe::all()
+            .iter()
+            .flat_map(|recipe| recipe.materials.keys())
+            .collect();
+        for category in [Category::Common, Category::Rare, Category::Legendary] {
+            let unused = Class::random_where(category, |class| {
+                class.drops.iter().any(|drop| !used.contains(&drop))
+            });
+            assert!(unused.is_none());
+        }
+    }
+}
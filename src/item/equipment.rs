@@ -1,13 +1,56 @@
 use core::fmt;
 
 use crate::character::class as character;
+use crate::character::{Element, StatusEffect};
+use crate::config::Config;
 use serde::{Deserialize, Serialize};
 
 pub trait Equipment: fmt::Display {
-    fn new(level: i32) -> Self;
+    fn new(level: i32) -> Self
+    where
+        Self: Sized;
 
     fn level(&self) -> i32;
 
+    fn set_level(&mut self, level: i32);
+
+    /// The extra bonuses rolled for this piece of equipment.
+    fn affixes(&self) -> &[(Affix, i32)];
+
+    fn set_affixes(&mut self, affixes: Vec<(Affix, i32)>);
+
+    /// The uses left before the equipment breaks, if it wears out at all.
+    fn durability(&self) -> Option<i32> {
+        None
+    }
+
+    /// Use up the equipment once, e.g. on an attack or a block.
+    fn wear(&mut self) {}
+
+    fn repair(&mut self) {}
+
+    /// Whether the equipment is so worn out that it loses effectiveness,
+    /// which only happens if durability is enabled.
+    fn is_worn(&self) -> bool {
+        Config::get().durability
+            && matches!(self.durability(), Some(left) if left <= DURABILITY / 5)
+    }
+
+    /// The rarity tier, given by the amount of affixes.
+    fn rarity(&self) -> Rarity {
+        match self.affixes().len() {
+            0 => Rarity::Common,
+            1 => Rarity::Magic,
+            2 => Rarity::Rare,
+            _ => Rarity::Legendary,
+        }
+    }
+
+    /// Scale a base stat bonus according to the rarity of the equipment.
+    fn scaled(&self, value: f64) -> i32 {
+        (value * self.rarity().multiplier()).round() as i32
+    }
+
     /// How many strength points get added to the player when
     /// the item is equipped.
     fn strength(&self) -> i32 {
@@ -15,56 +58,575 @@ pub trait Equipment: fmt::Display {
         let player_strength = character::Class::player_first().strength.at(self.level());
 
         // calculate the added strength as a function of the player strength
-        (player_strength as f64 * 0.5).round() as i32
+        let strength = self.scaled(player_strength as f64 * 0.5);
+        if self.is_worn() {
+            strength / 2
+        } else {
+            strength
+        }
     }
 
-    fn is_upgrade_from(&self, maybe_other: &Option<&Self>) -> bool {
+    /// A rough measure of how good the equipment is, to compare it with
+    /// others of the same kind. Each level is worth more than an affix.
+    fn value(&self) -> i32 {
+        self.scaled(self.level() as f64 * 10.0) + 5 * self.affixes().len() as i32
+    }
+
+    fn is_upgrade_from(&self, maybe_other: &Option<&Self>) -> bool
+    where
+        Self: Sized,
+    {
         if let Some(equip) = maybe_other {
-            self.level() > equip.level()
+            self.value() > equip.value()
         } else {
             true
         }
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rarity {
+    Common,
+    Magic,
+    Rare,
+    Legendary,
+}
+
+impl Rarity {
+    fn multiplier(&self) -> f64 {
+        match self {
+            Rarity::Common => 1.0,
+            Rarity::Magic => 1.1,
+            Rarity::Rare => 1.25,
+            Rarity::Legendary => 1.5,
+        }
+    }
+}
+
+impl fmt::Display for Rarity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Rarity::Common => "common",
+            Rarity::Magic => "magic",
+            Rarity::Rare => "rare",
+            Rarity::Legendary => "legendary",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// How many uses swords and shields withstand before breaking.
+pub const DURABILITY: i32 = 100;
+
+/// How many affixes a piece of equipment can have, making it legendary.
+pub const MAX_AFFIXES: usize = 3;
+
+/// A bonus that can be rolled for equipment, on top of its regular stats.
+/// The bonuses of all the equipped items add up.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Affix {
+    /// Added speed points.
+    Speed,
+    /// Added chance percentage of critical hits.
+    Critical,
+    /// Percentage of the damage dealt that is restored as hp.
+    Lifesteal,
+    /// Chance percentage of poisoning the receiver of an attack.
+    Poison,
+    /// Added max mp points.
+    Mp,
+}
+
+impl Affix {
+    pub const ALL: [Affix; 5] = [
+        Affix::Speed,
+        Affix::Critical,
+        Affix::Lifesteal,
+        Affix::Poison,
+        Affix::Mp,
+    ];
+
+    pub fn is_percentage(&self) -> bool {
+        matches!(self, Affix::Critical | Affix::Lifesteal | Affix::Poison)
+    }
+}
+
+impl fmt::Display for Affix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Affix::Speed => "spd",
+            Affix::Critical => "crit",
+            Affix::Lifesteal => "lifesteal",
+            Affix::Poison => "poison",
+            Affix::Mp => "mp",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A piece of equipment of any kind, e.g. to keep it in the stash.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum Gear {
+    Sword(Sword),
+    Shield(Shield),
+    Armor(Armor),
+    Helmet(Helmet),
+    Ring(Ring),
+    Amulet(Amulet),
+}
+
+impl Gear {
+    /// The name of the equipment slot this piece goes into.
+    pub fn slot(&self) -> &'static str {
+        match self {
+            Gear::Sword(_) => "sword",
+            Gear::Shield(_) => "shield",
+            Gear::Armor(_) => "armor",
+            Gear::Helmet(_) => "helmet",
+            Gear::Ring(_) => "ring",
+            Gear::Amulet(_) => "amulet",
+        }
+    }
+
+    pub fn equipment(&self) -> &dyn Equipment {
+        match self {
+            Gear::Sword(sword) => sword,
+            Gear::Shield(shield) => shield,
+            Gear::Armor(armor) => armor,
+            Gear::Helmet(helmet) => helmet,
+            Gear::Ring(ring) => ring,
+            Gear::Amulet(amulet) => amulet,
+        }
+    }
+}
+
+impl fmt::Display for Gear {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.equipment().fmt(f)
+    }
+}
+
+/// Put the found equipment in the slot if it's an upgrade. Return the one
+/// that was left out, either the found or the replaced one, if any.
+pub fn keep_best<T: Equipment>(slot: &mut Option<T>, found: T) -> Option<T> {
+    if found.is_upgrade_from(&slot.as_ref()) {
+        slot.replace(found)
+    } else {
+        Some(found)
+    }
+}
+
+/// Append the rarity to the equipment description, unless it's common.
+fn fmt_rarity(equipment: &dyn Equipment, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match equipment.rarity() {
+        Rarity::Common => Ok(()),
+        rarity => write!(f, "({})", rarity),
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Sword {
     level: i32,
+    #[serde(default)]
+    element: Option<Element>,
+    #[serde(default)]
+    affixes: Vec<(Affix, i32)>,
+    /// Uses since the sword was last repaired.
+    #[serde(default)]
+    wear: i32,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Shield {
     level: i32,
+    #[serde(default)]
+    affixes: Vec<(Affix, i32)>,
+    /// Uses since the shield was last repaired.
+    #[serde(default)]
+    wear: i32,
+}
+
+impl Sword {
+    pub fn elemental(level: i32, element: Element) -> Self {
+        Self {
+            level,
+            element: Some(element),
+            affixes: Vec::new(),
+            wear: 0,
+        }
+    }
+
+    /// Return the status that attacks with this sword can inflict, if it has
+    /// an elemental property, and the ratio to inflict it.
+    pub fn inflicted_status_effect(&self) -> Option<(StatusEffect, u32)> {
+        self.element
+            .and_then(|element| element.status_effect())
+            .map(|status| (status, 5))
+    }
+
+    pub fn element(&self) -> Option<Element> {
+        self.element
+    }
 }
 
 impl fmt::Display for Sword {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "sword[{}]", self.level())
+        write!(f, "sword[{}]", self.level())?;
+        if let Some(element) = self.element {
+            write!(f, "+{}", element)?;
+        }
+        fmt_rarity(self, f)
     }
 }
 
 impl Equipment for Sword {
     fn new(level: i32) -> Self {
-        Self { level }
+        Self {
+            level,
+            element: None,
+            affixes: Vec::new(),
+            wear: 0,
+        }
     }
 
     fn level(&self) -> i32 {
         self.level
     }
+
+    fn set_level(&mut self, level: i32) {
+        self.level = level;
+    }
+
+    fn affixes(&self) -> &[(Affix, i32)] {
+        &self.affixes
+    }
+
+    fn set_affixes(&mut self, affixes: Vec<(Affix, i32)>) {
+        self.affixes = affixes;
+    }
+
+    fn durability(&self) -> Option<i32> {
+        Some(DURABILITY - self.wear)
+    }
+
+    fn wear(&mut self) {
+        self.wear = (self.wear + 1).min(DURABILITY);
+    }
+
+    fn repair(&mut self) {
+        self.wear = 0;
+    }
+
+    /// An elemental sword is also an upgrade over a plain one of the same value.
+    fn is_upgrade_from(&self, maybe_other: &Option<&Self>) -> bool {
+        if let Some(sword) = maybe_other {
+            self.value() > sword.value()
+                || (self.value() == sword.value()
+                    && self.element.is_some()
+                    && sword.element.is_none())
+        } else {
+            true
+        }
+    }
 }
 
 impl fmt::Display for Shield {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "shield[{}]", self.level())
+        write!(f, "shield[{}]", self.level())?;
+        fmt_rarity(self, f)
     }
 }
 
 impl Equipment for Shield {
     fn new(level: i32) -> Self {
-        Self { level }
+        Self {
+            level,
+            affixes: Vec::new(),
+            wear: 0,
+        }
     }
 
     fn level(&self) -> i32 {
         self.level
     }
+
+    fn set_level(&mut self, level: i32) {
+        self.level = level;
+    }
+
+    fn affixes(&self) -> &[(Affix, i32)] {
+        &self.affixes
+    }
+
+    fn set_affixes(&mut self, affixes: Vec<(Affix, i32)>) {
+        self.affixes = affixes;
+    }
+
+    fn durability(&self) -> Option<i32> {
+        Some(DURABILITY - self.wear)
+    }
+
+    fn wear(&mut self) {
+        self.wear = (self.wear + 1).min(DURABILITY);
+    }
+
+    fn repair(&mut self) {
+        self.wear = 0;
+    }
+}
+
+/// Increases the max hp of the hero.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Armor {
+    level: i32,
+    #[serde(default)]
+    affixes: Vec<(Affix, i32)>,
+}
+
+/// Adds to the hero's defense, although less than a shield.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Helmet {
+    level: i32,
+    #[serde(default)]
+    affixes: Vec<(Affix, i32)>,
+}
+
+/// An accessory that increases the hero's speed.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Ring {
+    level: i32,
+    #[serde(default)]
+    affixes: Vec<(Affix, i32)>,
+}
+
+/// An accessory that increases the hero's max mp.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Amulet {
+    level: i32,
+    #[serde(default)]
+    affixes: Vec<(Affix, i32)>,
+}
+
+impl Armor {
+    pub fn hp(&self) -> i32 {
+        let player_hp = character::Class::player_first().hp.at(self.level);
+        self.scaled(player_hp as f64 * 0.3)
+    }
+}
+
+impl fmt::Display for Armor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "armor[{}]", self.level())?;
+        fmt_rarity(self, f)
+    }
+}
+
+impl Equipment for Armor {
+    fn new(level: i32) -> Self {
+        Self {
+            level,
+            affixes: Vec::new(),
+        }
+    }
+
+    fn level(&self) -> i32 {
+        self.level
+    }
+
+    fn set_level(&mut self, level: i32) {
+        self.level = level;
+    }
+
+    fn affixes(&self) -> &[(Affix, i32)] {
+        &self.affixes
+    }
+
+    fn set_affixes(&mut self, affixes: Vec<(Affix, i32)>) {
+        self.affixes = affixes;
+    }
+}
+
+impl fmt::Display for Helmet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "helmet[{}]", self.level())?;
+        fmt_rarity(self, f)
+    }
+}
+
+impl Equipment for Helmet {
+    fn new(level: i32) -> Self {
+        Self {
+            level,
+            affixes: Vec::new(),
+        }
+    }
+
+    fn level(&self) -> i32 {
+        self.level
+    }
+
+    fn set_level(&mut self, level: i32) {
+        self.level = level;
+    }
+
+    fn affixes(&self) -> &[(Affix, i32)] {
+        &self.affixes
+    }
+
+    fn set_affixes(&mut self, affixes: Vec<(Affix, i32)>) {
+        self.affixes = affixes;
+    }
+
+    fn strength(&self) -> i32 {
+        let player_strength = character::Class::player_first().strength.at(self.level);
+        self.scaled(player_strength as f64 * 0.25)
+    }
+}
+
+impl Ring {
+    pub fn speed(&self) -> i32 {
+        let player_speed = character::Class::player_first().speed.at(self.level);
+        self.scaled(player_speed as f64 * 0.25)
+    }
+}
+
+impl fmt::Display for Ring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ring[{}]", self.level())?;
+        fmt_rarity(self, f)
+    }
+}
+
+impl Equipment for Ring {
+    fn new(level: i32) -> Self {
+        Self {
+            level,
+            affixes: Vec::new(),
+        }
+    }
+
+    fn level(&self) -> i32 {
+        self.level
+    }
+
+    fn set_level(&mut self, level: i32) {
+        self.level = level;
+    }
+
+    fn affixes(&self) -> &[(Affix, i32)] {
+        &self.affixes
+    }
+
+    fn set_affixes(&mut self, affixes: Vec<(Affix, i32)>) {
+        self.affixes = affixes;
+    }
+}
+
+impl Amulet {
+    pub fn mp(&self) -> i32 {
+        // based on hp since the first player class may not use magic
+        let player_hp = character::Class::player_first().hp.at(self.level);
+        self.scaled(player_hp as f64 * 0.2)
+    }
+}
+
+impl fmt::Display for Amulet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "amulet[{}]", self.level())?;
+        fmt_rarity(self, f)
+    }
+}
+
+impl Equipment for Amulet {
+    fn new(level: i32) -> Self {
+        Self {
+            level,
+            affixes: Vec::new(),
+        }
+    }
+
+    fn level(&self) -> i32 {
+        self.level
+    }
+
+    fn set_level(&mut self, level: i32) {
+        self.level = level;
+    }
+
+    fn affixes(&self) -> &[(Affix, i32)] {
+        &self.affixes
+    }
+
+    fn set_affixes(&mut self, affixes: Vec<(Affix, i32)>) {
+        self.affixes = affixes;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rarity() {
+        let mut sword = Sword::new(10);
+        assert_eq!(Rarity::Common, sword.rarity());
+        assert_eq!("sword[10]", sword.to_string());
+        let strength = sword.strength();
+
+        sword.set_affixes(vec![(Affix::Speed, 2), (Affix::Lifesteal, 10)]);
+        assert_eq!(Rarity::Rare, sword.rarity());
+        assert_eq!("sword[10](rare)", sword.to_string());
+        assert!(sword.strength() > strength);
+    }
+
+    #[test]
+    fn test_durability() {
+        let mut sword = Sword::new(10);
+        let strength = sword.strength();
+        assert_eq!(Some(DURABILITY), sword.durability());
+        assert_eq!(None, Ring::new(10).durability());
+
+        for _ in 0..DURABILITY - DURABILITY / 5 {
+            sword.wear();
+        }
+        assert_eq!(Some(DURABILITY / 5), sword.durability());
+
+        // durability is disabled by default, so there's no penalty
+        assert!(!sword.is_worn());
+        assert_eq!(strength, sword.strength());
+
+        for _ in 0..DURABILITY {
+            sword.wear();
+        }
+        assert_eq!(Some(0), sword.durability());
+
+        sword.repair();
+        assert_eq!(Some(DURABILITY), sword.durability());
+    }
+
+    #[test]
+    fn test_upgrade() {
+        let common = Shield::new(10);
+        let mut magic = Shield::new(10);
+        magic.set_affixes(vec![(Affix::Critical, 5)]);
+        assert!(magic.is_upgrade_from(&Some(&common)));
+        assert!(!common.is_upgrade_from(&Some(&magic)));
+
+        // a higher level is still better than a few affixes
+        let mut legendary = Shield::new(5);
+        legendary.set_affixes(vec![
+            (Affix::Critical, 5),
+            (Affix::Speed, 1),
+            (Affix::Mp, 2),
+        ]);
+        assert!(common.is_upgrade_from(&Some(&legendary)));
+
+        // the element breaks ties between swords
+        let plain = Sword::new(5);
+        let fire = Sword::elemental(5, Element::Fire);
+        assert!(fire.is_upgrade_from(&Some(&plain)));
+        assert!(!plain.is_upgrade_from(&Some(&fire)));
+    }
 }
@@ -1,16 +1,30 @@
 use core::fmt;
 
 use crate::character::class as character;
+use crate::character::StatusEffect;
 use crate::event::Event;
 use crate::game;
 use serde::{Deserialize, Serialize};
 
+pub mod craft;
 pub mod equipment;
 pub mod shop;
 
 #[typetag::serde(tag = "type")]
 pub trait Item {
     fn apply(&self, game: &mut game::Game);
+
+    /// Whether the item has any use in the current game state,
+    /// so it's not consumed otherwise.
+    fn is_usable(&self, _game: &game::Game) -> bool {
+        true
+    }
+
+    /// The item as sold at the shop, to know its price, unless
+    /// it can't be bought or sold.
+    fn as_shoppable(&self) -> Option<&dyn shop::Shoppable> {
+        None
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -32,6 +46,10 @@ impl fmt::Display for Potion {
 
 #[typetag::serde]
 impl Item for Potion {
+    fn as_shoppable(&self) -> Option<&dyn shop::Shoppable> {
+        Some(self)
+    }
+
     fn apply(&self, game: &mut game::Game) {
         let to_restore = character::Class::player_first().hp.at(self.level) / 2;
         let recovered = game.player.heal(to_restore);
@@ -59,8 +77,12 @@ impl Escape {
 
 #[typetag::serde]
 impl Item for Escape {
+    fn as_shoppable(&self) -> Option<&dyn shop::Shoppable> {
+        Some(self)
+    }
+
     fn apply(&self, game: &mut game::Game) {
-        game.visit_home();
+        game.visit_save_point();
     }
 }
 
@@ -81,6 +103,10 @@ impl Remedy {
 
 #[typetag::serde]
 impl Item for Remedy {
+    fn as_shoppable(&self) -> Option<&dyn shop::Shoppable> {
+        Some(self)
+    }
+
     fn apply(&self, game: &mut game::Game) {
         let healed = game.player.maybe_remove_status_effect();
         Event::emit(
@@ -101,6 +127,126 @@ impl fmt::Display for Remedy {
     }
 }
 
+/// Removes a specific negative status effect, as opposed to the remedy
+/// which removes any of them.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Cure {
+    status: StatusEffect,
+}
+
+impl Cure {
+    pub fn antidote() -> Self {
+        Self {
+            status: StatusEffect::Poison,
+        }
+    }
+
+    pub fn salve() -> Self {
+        Self {
+            status: StatusEffect::Burn,
+        }
+    }
+
+    pub fn tonic() -> Self {
+        Self {
+            status: StatusEffect::Weakness,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self.status {
+            StatusEffect::Poison => "antidote",
+            StatusEffect::Burn => "salve",
+            _ => "tonic",
+        }
+    }
+}
+
+#[typetag::serde]
+impl Item for Cure {
+    fn as_shoppable(&self) -> Option<&dyn shop::Shoppable> {
+        Some(self)
+    }
+
+    fn apply(&self, game: &mut game::Game) {
+        let healed = game.player.cure_status_effect(self.status);
+        Event::emit(
+            game,
+            Event::Heal {
+                item: Some(self.name()),
+                recovered_hp: 0,
+                recovered_mp: 0,
+                healed,
+            },
+        );
+    }
+}
+
+impl fmt::Display for Cure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Grants a positive status effect, replacing the current one unless
+/// the hero suffers a negative one.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Buff {
+    status: StatusEffect,
+}
+
+impl Buff {
+    pub fn herb() -> Self {
+        Self {
+            status: StatusEffect::Regen,
+        }
+    }
+
+    pub fn coffee() -> Self {
+        Self {
+            status: StatusEffect::Haste,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self.status {
+            StatusEffect::Regen => "herb",
+            _ => "coffee",
+        }
+    }
+}
+
+#[typetag::serde]
+impl Item for Buff {
+    fn as_shoppable(&self) -> Option<&dyn shop::Shoppable> {
+        Some(self)
+    }
+
+    fn is_usable(&self, game: &game::Game) -> bool {
+        game.player
+            .status_effect
+            .iter()
+            .all(|status| status.is_positive())
+    }
+
+    fn apply(&self, game: &mut game::Game) {
+        game.player.set_status_effect(self.status);
+        Event::emit(
+            game,
+            Event::Buff {
+                item: self.name(),
+                status: self.status,
+            },
+        );
+    }
+}
+
+impl fmt::Display for Buff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Ether {
     level: i32,
@@ -120,6 +266,10 @@ impl fmt::Display for Ether {
 
 #[typetag::serde]
 impl Item for Ether {
+    fn as_shoppable(&self) -> Option<&dyn shop::Shoppable> {
+        Some(self)
+    }
+
     fn apply(&self, game: &mut game::Game) {
         let to_restore = game
             .player
@@ -140,3 +290,32 @@ impl Item for Ether {
         );
     }
 }
+
+/// Opens the locked chest at the hero's location, if the key level is enough.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Key {
+    level: i32,
+}
+
+impl Key {
+    pub fn new(level: i32) -> Self {
+        Self { level }
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "key[{}]", self.level)
+    }
+}
+
+#[typetag::serde]
+impl Item for Key {
+    fn is_usable(&self, game: &game::Game) -> bool {
+        game.can_unlock(self.level)
+    }
+
+    fn apply(&self, game: &mut game::Game) {
+        game.unlock_chest(self.level);
+    }
+}
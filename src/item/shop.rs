@@ -1,72 +1,432 @@
+use std::cmp::min;
+use std::fmt;
 use std::{collections::HashMap, fmt::Display};
 
-use super::equipment::{Equipment, Shield, Sword};
+use super::equipment::{Amulet, Armor, Equipment, Gear, Helmet, Ring, Shield, Sword, DURABILITY};
 use crate::character::Character;
 use crate::event::Event;
-use crate::game::Game;
+use crate::game::{today, Game};
 use crate::log;
+use crate::randomizer::{random, seeded, Randomizer};
 use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+/// How much more than their regular price the special offers cost.
+const PREMIUM: f64 = 1.5;
 
 /// Print the list of available items and their price.
 pub fn list(game: &Game) -> Result<()> {
-    if !game.location.is_home() {
-        bail!("Shop is only allowed at home.");
+    if !game.location.is_home() && !game.location.is_town() {
+        bail!("Shop is only allowed at home and in towns.");
+    }
+
+    log::shop_list(game, offers(game, today()));
+    Ok(())
+}
+
+/// How many units of an item to buy.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Quantity {
+    Units(usize),
+    /// As many as the gold left after the rest of the purchase allows.
+    Max,
+}
+
+/// Buy the given items and add them to the game. The total cost is checked
+/// up front so either all of them are bought or none.
+pub fn buy(game: &mut Game, orders: &[(String, Quantity)]) -> Result<()> {
+    if !game.location.is_home() && !game.location.is_town() {
+        bail!("Shop is only allowed at home and in towns.");
     }
 
-    let items = available_items(&game.player)
+    let day = today();
+    let offers = offers(game, day)
         .into_iter()
-        .map(|(_, item)| item)
-        .collect::<Vec<Box<dyn Shoppable>>>();
-    log::shop_list(game, items);
+        .map(|offer| (offer.name.clone(), offer))
+        .collect::<HashMap<String, Offer>>();
+
+    let mut cart = Vec::new();
+    let mut total = 0;
+    for (name, quantity) in orders {
+        let offer = match offers.get(name) {
+            Some(offer) => offer,
+            None => bail!("Item not available."),
+        };
+        if let Quantity::Units(units) = quantity {
+            // checked before adding up, so huge quantities can't overflow the total
+            if *units > offer.stock {
+                bail!("Not enough stock.");
+            }
+            total += offer.item.cost() * *units as i32;
+            cart.push((offer, *units));
+        }
+    }
+
+    // fill the rest of the cart with the gold and stock that's left
+    for (name, quantity) in orders {
+        if let Quantity::Max = quantity {
+            let offer = &offers[name];
+            let left = offer.stock.saturating_sub(in_cart(&cart, name));
+            if left == 0 {
+                bail!("Not enough stock.");
+            }
+            let affordable = (game.gold - total).max(0) / offer.item.cost();
+            let units = min(affordable as usize, left);
+            total += offer.item.cost() * units as i32;
+            cart.push((offer, units));
+        }
+    }
+
+    if cart
+        .iter()
+        .any(|(offer, _)| in_cart(&cart, &offer.name) > offer.stock)
+    {
+        bail!("Not enough stock.");
+    }
+    if total > game.gold || cart.iter().any(|(_, units)| *units == 0) {
+        bail!("Not enough gold.");
+    }
+
+    for (offer, units) in cart {
+        for _ in 0..units {
+            offer.item.buy(game)?;
+        }
+        game.purchases.add(day, &offer.name, units);
+    }
+    log::purchase(game, total);
     Ok(())
 }
 
-/// Buy an item and add it to the game.
-pub fn buy(game: &mut Game, item: &str) -> Result<()> {
+/// Pay to raise the level of the equipment worn in the given slot or, when
+/// enchanting, to add a random affix to it. The price is that of the improved
+/// piece, multiplied by its affixes when enchanting, and gets lower chances of
+/// success the further the equipment is above the hero. The gold is spent even
+/// if the upgrade fails.
+pub fn upgrade(game: &mut Game, slot: &str, enchant: bool) -> Result<()> {
     if !game.location.is_home() {
-        bail!("Shop is only allowed at home.");
+        bail!("Upgrading is only allowed at home.");
     }
 
-    let player = &mut game.player;
-    let mut items = available_items(player)
-        .into_iter()
-        .collect::<HashMap<String, Box<dyn Shoppable>>>();
-    if let Some(item) = items.remove(item) {
-        item.buy(game)?;
-        Ok(())
+    let original = match game.player.gear(slot) {
+        Some(gear) => gear,
+        None => bail!("Nothing equipped in that slot."),
+    };
+    let level = original.equipment().level();
+
+    // the upgrade is applied to price it, and undone if it's not paid or fails
+    let difficulty = if enchant {
+        let affixes = original.equipment().affixes();
+        let (affix, value) = match random().enchantment(level, affixes) {
+            Some(enchantment) => enchantment,
+            None => bail!("The equipment can't hold more bonuses."),
+        };
+        game.add_affix(slot, affix, value)?;
+        affixes.len() as u32 + 1
     } else {
-        bail!("Item not available.")
+        game.add_level(slot)?;
+        (level + 1 - game.player.rounded_level()).max(0) as u32
+    };
+
+    let upgraded = game.player.gear(slot).unwrap();
+    let cost = if enchant {
+        upgraded.cost() * difficulty as i32
+    } else {
+        upgraded.cost()
+    };
+    if game.gold < cost {
+        game.player.equip(original);
+        bail!("Not enough gold.");
     }
+    game.gold -= cost;
+
+    let success = random().upgrade_succeeds(difficulty);
+    if !success {
+        game.player.equip(original);
+    }
+    Event::emit(
+        game,
+        Event::ItemUpgraded {
+            item: upgraded.to_string(),
+            cost,
+            success,
+        },
+    );
+    Ok(())
 }
 
-/// Build a list of items currently available at the shop
-fn available_items(player: &Character) -> Vec<(String, Box<dyn Shoppable>)> {
-    let mut items = Vec::<(String, Box<dyn Shoppable>)>::new();
+/// Repair the worn out equipment of the given slot, or all of it. Fixing a
+/// piece that's about to break costs half of its price.
+pub fn repair(game: &mut Game, slot: Option<&str>) -> Result<()> {
+    if !game.location.is_home() && !game.location.is_town() {
+        bail!("Shop is only allowed at home and in towns.");
+    }
+
+    let slots = match slot {
+        Some(slot) => vec![slot],
+        None => vec!["sword", "shield", "armor", "helmet", "ring", "amulet"],
+    };
+    let mut total = 0;
+    let mut worn = Vec::new();
+    for slot in slots {
+        if let Some(gear) = game.player.gear(slot) {
+            let wear = gear
+                .equipment()
+                .durability()
+                .map_or(0, |left| DURABILITY - left);
+            if wear > 0 {
+                total += gear.cost() * wear / DURABILITY / 2;
+                worn.push(slot);
+            }
+        }
+    }
+
+    if worn.is_empty() {
+        bail!("Nothing to repair.");
+    }
+    if game.gold < total {
+        bail!("Not enough gold.");
+    }
+    game.gold -= total;
+    for slot in worn {
+        if let Some(equipment) = game.player.equipment_mut(slot) {
+            equipment.repair();
+        }
+    }
+    log::purchase(game, total);
+    Ok(())
+}
+
+/// The units of the given item already in the cart.
+fn in_cart(cart: &[(&Offer, usize)], name: &str) -> usize {
+    cart.iter()
+        .filter(|(offer, _)| offer.name == name)
+        .map(|(_, units)| units)
+        .sum()
+}
+
+/// Sell up to the given quantity of an item from the inventory or a piece
+/// of equipment from the stash. Equipment can be referred to by its
+/// description, or by its slot to sell the least valuable of that kind.
+pub fn sell(game: &mut Game, name: &str, quantity: usize) -> Result<()> {
+    if !game.location.is_home() && !game.location.is_town() {
+        bail!("Shop is only allowed at home and in towns.");
+    }
+
+    // nothing is sold unless the whole quantity is available
+    let available = match game.inventory.get(name) {
+        Some(items) => items.len(),
+        None => game.stash_matches(name).len(),
+    };
+    if quantity == 0 {
+        bail!("Invalid quantity.");
+    } else if available == 0 {
+        bail!("Item not found.");
+    } else if available < quantity {
+        bail!("Not enough items.");
+    }
+
+    for _ in 0..quantity {
+        let (item, gold) = if let Some(items) = game.inventory.get_mut(name) {
+            let price = items
+                .last()
+                .and_then(|item| item.as_shoppable())
+                .map(|item| (item.to_string(), item.sell_price()));
+            if price.is_none() {
+                bail!("The shop doesn't buy that.");
+            }
+            items.pop();
+            if items.is_empty() {
+                game.inventory.remove(name);
+            }
+            price.unwrap()
+        } else {
+            let (index, _) = game
+                .stash_matches(name)
+                .into_iter()
+                .min_by_key(|(_, gear)| gear.equipment().value())
+                .unwrap();
+            let gear = game.stash.remove(index);
+            (gear.to_string(), gear.sell_price())
+        };
+
+        game.gold += gold;
+        Event::emit(game, Event::ItemSold { item, gold });
+    }
+    Ok(())
+}
+
+/// An item for sale at the shop, with the units left for the day.
+pub struct Offer {
+    pub name: String,
+    pub item: Box<dyn Shoppable>,
+    pub stock: usize,
+}
+
+/// Units of each item bought on a given day, to enforce the daily stock.
+#[derive(Serialize, Deserialize, Default)]
+pub struct Purchases {
+    day: u64,
+    units: HashMap<String, usize>,
+}
+
+impl Purchases {
+    fn bought(&self, day: u64, name: &str) -> usize {
+        if self.day == day {
+            self.units.get(name).copied().unwrap_or_default()
+        } else {
+            0
+        }
+    }
+
+    fn add(&mut self, day: u64, name: &str, units: usize) {
+        if self.day != day {
+            self.day = day;
+            self.units.clear();
+        }
+        *self.units.entry(name.to_string()).or_default() += units;
+    }
+}
+
+/// A special offer, sold above its regular price.
+struct Premium(Box<dyn Shoppable>);
+
+impl Display for Premium {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Shoppable for Premium {
+    fn cost(&self) -> i32 {
+        (self.0.cost() as f64 * PREMIUM) as i32
+    }
+
+    fn add_to(&self, game: &mut Game) {
+        self.0.add_to(game)
+    }
+}
+
+/// Build the list of items available at the shop on the given day, minus
+/// the units already bought. The stock rotates daily and is different for
+/// each hero.
+fn offers(game: &Game, day: u64) -> Vec<Offer> {
+    let randomizer = seeded(game.shop_seed(day));
+    let player = &game.player;
     let level = player.rounded_level();
+    let mut items = Vec::<(String, Box<dyn Shoppable>, i32)>::new();
+
+    // town shops only sell consumables
+    let at_home = game.location.is_home();
 
+    // the stock is rolled for every item, even the ones that are left out,
+    // so it doesn't change during the day as the hero changes
     let sword = Sword::new(level);
-    if sword.is_upgrade_from(&player.sword.as_ref()) {
-        items.push(("sword".to_string(), Box::new(sword)));
+    let stock = randomizer.shop_stock(1);
+    if at_home && sword.is_upgrade_from(&player.sword.as_ref()) {
+        items.push(("sword".to_string(), Box::new(sword), stock));
     }
 
     let shield = Shield::new(level);
-    if shield.is_upgrade_from(&player.shield.as_ref()) {
-        items.push(("shield".to_string(), Box::new(shield)));
+    let stock = randomizer.shop_stock(1);
+    if at_home && shield.is_upgrade_from(&player.shield.as_ref()) {
+        items.push(("shield".to_string(), Box::new(shield), stock));
     }
 
+    let armor = Armor::new(level);
+    let stock = randomizer.shop_stock(1);
+    if at_home && armor.is_upgrade_from(&player.armor.as_ref()) {
+        items.push(("armor".to_string(), Box::new(armor), stock));
+    }
+
+    let helmet = Helmet::new(level);
+    let stock = randomizer.shop_stock(1);
+    if at_home && helmet.is_upgrade_from(&player.helmet.as_ref()) {
+        items.push(("helmet".to_string(), Box::new(helmet), stock));
+    }
+
+    let ring = Ring::new(level);
+    let stock = randomizer.shop_stock(1);
+    if at_home && ring.is_upgrade_from(&player.ring.as_ref()) {
+        items.push(("ring".to_string(), Box::new(ring), stock));
+    }
+
+    let amulet = Amulet::new(level);
+    let stock = randomizer.shop_stock(1);
+    if at_home && amulet.is_upgrade_from(&player.amulet.as_ref()) {
+        items.push(("amulet".to_string(), Box::new(amulet), stock));
+    }
+
+    // potions are always in stock
     let potion = super::Potion::new(level);
-    items.push(("potion".to_string(), Box::new(potion)));
+    let stock = 5 + randomizer.shop_stock(5);
+    items.push(("potion".to_string(), Box::new(potion), stock));
 
     let ether = super::Ether::new(level);
-    items.push(("ether".to_string(), Box::new(ether)));
+    let stock = randomizer.shop_stock(5);
+    items.push(("ether".to_string(), Box::new(ether), stock));
 
     let remedy = super::Remedy::new();
-    items.push(("remedy".to_string(), Box::new(remedy)));
+    let stock = randomizer.shop_stock(3);
+    items.push(("remedy".to_string(), Box::new(remedy), stock));
+
+    for cure in [
+        super::Cure::antidote(),
+        super::Cure::salve(),
+        super::Cure::tonic(),
+    ] {
+        let stock = randomizer.shop_stock(3);
+        items.push((cure.to_string(), Box::new(cure), stock));
+    }
+
+    for buff in [super::Buff::herb(), super::Buff::coffee()] {
+        let stock = randomizer.shop_stock(3);
+        items.push((buff.to_string(), Box::new(buff), stock));
+    }
 
     let escape = super::Escape::new();
-    items.push(("escape".to_string(), Box::new(escape)));
+    let stock = randomizer.shop_stock(2);
+    items.push(("escape".to_string(), Box::new(escape), stock));
+
+    if randomizer.shop_offer() && at_home {
+        if let Some(offer) = special_offer(player, &randomizer) {
+            items.push((offer.to_string(), Box::new(Premium(offer)), 1));
+        }
+    }
 
     items
+        .into_iter()
+        .map(|(name, item, stock)| {
+            let stock = (stock as usize).saturating_sub(game.purchases.bought(day, &name));
+            Offer { name, item, stock }
+        })
+        .filter(|offer| offer.stock > 0)
+        .collect()
+}
+
+/// An item of a higher level than the regular stock. Equipment is only
+/// offered if it's an upgrade for the hero.
+fn special_offer(player: &Character, randomizer: &dyn Randomizer) -> Option<Box<dyn Shoppable>> {
+    let level = player.rounded_level() + 5;
+    match randomizer.range(7) {
+        0 => if_upgrade(Sword::new(level), &player.sword),
+        1 => if_upgrade(Shield::new(level), &player.shield),
+        2 => if_upgrade(Armor::new(level), &player.armor),
+        3 => if_upgrade(Helmet::new(level), &player.helmet),
+        4 => if_upgrade(Ring::new(level), &player.ring),
+        5 => if_upgrade(Amulet::new(level), &player.amulet),
+        _ => Some(Box::new(super::Potion::new(level + 5))),
+    }
+}
+
+fn if_upgrade<T: Equipment + Shoppable + 'static>(
+    item: T,
+    current: &Option<T>,
+) -> Option<Box<dyn Shoppable>> {
+    if item.is_upgrade_from(&current.as_ref()) {
+        Some(Box::new(item))
+    } else {
+        None
+    }
 }
 
 pub trait Shoppable: Display {
@@ -88,25 +448,87 @@ pub trait Shoppable: Display {
         Ok(())
     }
     fn add_to(&self, game: &mut Game);
+
+    /// How much gold the shop pays for the item.
+    fn sell_price(&self) -> i32 {
+        self.cost() / 2
+    }
+}
+
+impl Shoppable for Gear {
+    fn cost(&self) -> i32 {
+        match self {
+            Gear::Sword(sword) => sword.cost(),
+            Gear::Shield(shield) => shield.cost(),
+            Gear::Armor(armor) => armor.cost(),
+            Gear::Helmet(helmet) => helmet.cost(),
+            Gear::Ring(ring) => ring.cost(),
+            Gear::Amulet(amulet) => amulet.cost(),
+        }
+    }
+
+    fn add_to(&self, game: &mut Game) {
+        game.wear(self.clone())
+    }
 }
 
 impl Shoppable for Sword {
     fn cost(&self) -> i32 {
-        self.level() * 500
+        self.scaled(self.level() as f64 * 500.0)
     }
 
     fn add_to(&self, game: &mut Game) {
-        game.player.sword = Some(self.clone())
+        game.wear(Gear::Sword(self.clone()))
     }
 }
 
 impl Shoppable for Shield {
     fn cost(&self) -> i32 {
-        self.level() * 500
+        self.scaled(self.level() as f64 * 500.0)
     }
 
     fn add_to(&self, game: &mut Game) {
-        game.player.shield = Some(self.clone())
+        game.wear(Gear::Shield(self.clone()))
+    }
+}
+
+impl Shoppable for Armor {
+    fn cost(&self) -> i32 {
+        self.scaled(self.level() as f64 * 500.0)
+    }
+
+    fn add_to(&self, game: &mut Game) {
+        game.wear(Gear::Armor(self.clone()))
+    }
+}
+
+impl Shoppable for Helmet {
+    fn cost(&self) -> i32 {
+        self.scaled(self.level() as f64 * 300.0)
+    }
+
+    fn add_to(&self, game: &mut Game) {
+        game.wear(Gear::Helmet(self.clone()))
+    }
+}
+
+impl Shoppable for Ring {
+    fn cost(&self) -> i32 {
+        self.scaled(self.level() as f64 * 400.0)
+    }
+
+    fn add_to(&self, game: &mut Game) {
+        game.wear(Gear::Ring(self.clone()))
+    }
+}
+
+impl Shoppable for Amulet {
+    fn cost(&self) -> i32 {
+        self.scaled(self.level() as f64 * 400.0)
+    }
+
+    fn add_to(&self, game: &mut Game) {
+        game.wear(Gear::Amulet(self.clone()))
     }
 }
 
@@ -140,6 +562,26 @@ impl Shoppable for super::Remedy {
     }
 }
 
+impl Shoppable for super::Cure {
+    fn cost(&self) -> i32 {
+        250
+    }
+
+    fn add_to(&self, game: &mut Game) {
+        game.add_item(self.name(), Box::new(self.clone()));
+    }
+}
+
+impl Shoppable for super::Buff {
+    fn cost(&self) -> i32 {
+        600
+    }
+
+    fn add_to(&self, game: &mut Game) {
+        game.add_item(self.name(), Box::new(self.clone()));
+    }
+}
+
 impl Shoppable for super::Ether {
     fn cost(&self) -> i32 {
         self.level * 250
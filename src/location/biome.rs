@@ -0,0 +1,100 @@
+use super::Location;
+use crate::character::class::Category;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+
+/// A region of the filesystem with its own kind of enemies.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Biome {
+    pub name: String,
+
+    /// Path pattern of the locations in the biome, e.g. `/tmp/**`.
+    pub location: String,
+
+    /// Names of the enemy classes that can be found in the biome.
+    /// If empty, any enemy can be found.
+    #[serde(default)]
+    pub enemies: Vec<String>,
+
+    /// Replace the distance based chances of finding each enemy category.
+    #[serde(default)]
+    pub weights: Option<Weights>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Weights {
+    pub common: i32,
+    pub rare: i32,
+    pub legendary: i32,
+}
+
+static BIOMES: OnceCell<Vec<Biome>> = OnceCell::new();
+
+impl Biome {
+    /// Customize the biome definitions based on an input yaml byte array.
+    pub fn load(bytes: &[u8]) {
+        BIOMES.set(from_bytes(bytes)).unwrap();
+    }
+
+    pub fn all() -> &'static [Biome] {
+        BIOMES.get_or_init(default_biomes)
+    }
+
+    /// Return the first biome that includes the given location, if any.
+    pub fn at(location: &Location) -> Option<&'static Self> {
+        Self::all()
+            .iter()
+            .find(|biome| location.matches(&biome.location))
+    }
+
+    pub fn allows(&self, class_name: &str) -> bool {
+        self.enemies.is_empty() || self.enemies.iter().any(|name| name == class_name)
+    }
+
+    /// Return the chance weight of the given enemy category,
+    /// if overridden by the biome.
+    pub fn weight(&self, category: &Category) -> Option<i32> {
+        self.weights.as_ref().map(|weights| match category {
+            Category::Common => weights.common,
+            Category::Rare => weights.rare,
+            Category::Legendary => weights.legendary,
+            Category::Player => 0,
+        })
+    }
+}
+
+fn default_biomes() -> Vec<Biome> {
+    from_bytes(include_bytes!("biomes.yaml"))
+}
+
+fn from_bytes(bytes: &[u8]) -> Vec<Biome> {
+    serde_yaml::from_slice(bytes).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location_from(path: &str) -> Location {
+        Location::new(std::path::PathBuf::from(path))
+    }
+
+    #[test]
+    fn test_biome_at() {
+        let at = |path: &str| Biome::at(&location_from(path)).map(|b| b.name.as_str());
+
+        assert_eq!(Some("swamp"), at("/tmp"));
+        assert_eq!(Some("swamp"), at("/tmp/some/dir"));
+        assert_eq!(Some("ancient ruins"), at("/usr/lib"));
+        assert_eq!(None, at("/etc"));
+
+        let swamp = Biome::at(&location_from("/tmp")).unwrap();
+        assert!(swamp.allows("slime"));
+        assert!(!swamp.allows("dragon"));
+        assert_eq!(Some(1), swamp.weight(&Category::Legendary));
+
+        let ruins = Biome::at(&location_from("/usr")).unwrap();
+        assert_eq!(None, ruins.weight(&Category::Common));
+    }
+}
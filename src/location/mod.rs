@@ -0,0 +1,429 @@
+use crate::config::Config;
+use once_cell::unsync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::{fs, path};
+
+pub mod biome;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Location {
+    path: path::PathBuf,
+
+    /// The directory contents are only looked at once per location.
+    #[serde(skip)]
+    features: OnceCell<Features>,
+}
+
+impl Location {
+    fn new(path: path::PathBuf) -> Self {
+        Self {
+            path,
+            features: OnceCell::new(),
+        }
+    }
+
+    /// Build a location from the given path string.
+    /// The path is validated to exist and converted to it's canonical form.
+    pub fn from(path: &str) -> Result<Self, std::io::Error> {
+        // if input doesn't come from shell, we want to interpret ~ as home ourselves
+        let path = expand_home(&patch_oldpwd(path));
+        let path = path::Path::new(&path);
+        // this is a replacement to std::fs::canonicalize()
+        // that circumvents windows quirks with paths
+        let path = dunce::canonicalize(&path)?;
+        Ok(Self::new(path))
+    }
+
+    pub fn path_string(&self) -> String {
+        self.path.to_string_lossy().to_string()
+    }
+
+    pub fn home() -> Self {
+        Self::new(dirs::home_dir().unwrap())
+    }
+
+    pub fn is_home(&self) -> bool {
+        self.path == dirs::home_dir().unwrap()
+    }
+
+    /// Whether the location matches one of the configured towns
+    /// or contains the town marker file.
+    pub fn is_town(&self) -> bool {
+        let towns = &Config::get().towns;
+        towns.locations.iter().any(|pattern| self.matches(pattern))
+            || towns
+                .marker
+                .iter()
+                .any(|marker| self.path.join(marker).is_file())
+    }
+
+    pub fn is_rpg_dir(&self) -> bool {
+        self.path == dirs::home_dir().unwrap().join(".rpg")
+    }
+
+    /// Return a new location that it's one dir closer to the given destination.
+    pub fn go_to(&self, dest: &Self) -> Self {
+        let next = if dest.path.starts_with(&self.path) {
+            let self_len = self.path.components().count();
+            dest.path.components().take(self_len + 1).collect()
+        } else {
+            self.path.parent().unwrap().to_path_buf()
+        };
+        Self::new(next)
+    }
+
+    fn distance_from(&self, other: &Self) -> Distance {
+        let mut current = self.path.as_path();
+        let dest = other.path.as_path();
+
+        let mut distance = 0;
+        while !dest.starts_with(&current) {
+            current = current.parent().unwrap();
+            distance += 1;
+        }
+        let dest = dest.strip_prefix(current).unwrap();
+        let len = distance + dest.components().count() as i32;
+        Distance::from(len)
+    }
+
+    pub fn distance_from_home(&self) -> Distance {
+        self.distance_from(&Location::home())
+    }
+
+    /// Look at the directory contents to determine the features
+    /// that shape the enemies and treasure found in it.
+    /// Unreadable directories are treated as empty.
+    pub fn features(&self) -> &Features {
+        self.features.get_or_init(|| self.read_features())
+    }
+
+    fn read_features(&self) -> Features {
+        let mut features = Features {
+            is_infested: self.path.components().any(|c| {
+                let name = c.as_os_str();
+                INFESTED_DIRS.iter().any(|infested| name == *infested)
+            }),
+            is_hidden: self
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy().starts_with('.'))
+                .unwrap_or_default(),
+            ..Features::default()
+        };
+
+        if let Ok(entries) = fs::read_dir(&self.path) {
+            for entry in entries.flatten() {
+                features.file_count += 1;
+                if entry.file_name() == ".git" {
+                    features.is_repo = true;
+                } else if !features.has_large_files {
+                    if let Ok(metadata) = entry.metadata() {
+                        features.has_large_files =
+                            metadata.is_file() && metadata.len() >= LARGE_FILE_BYTES;
+                    }
+                }
+
+                // the rest of the entries can't change anything
+                if features.is_crowded() && features.is_repo && features.has_large_files {
+                    break;
+                }
+            }
+        }
+        features
+    }
+
+    /// A stable hash of the location path, used to derive
+    /// deterministic values from it.
+    pub fn seed(&self) -> u64 {
+        // FNV-1a, since std hashers aren't guaranteed to be stable across releases
+        self.path_string()
+            .bytes()
+            .fold(0xcbf29ce484222325, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+            })
+    }
+
+    pub fn biome(&self) -> Option<&'static biome::Biome> {
+        biome::Biome::at(self)
+    }
+
+    /// Return true if this location matches the given path pattern.
+    /// A leading `~` stands for the home dir, a `*` component matches any
+    /// single directory and a `**` component matches any number of them.
+    pub fn matches(&self, pattern: &str) -> bool {
+        let pattern = expand_home(pattern);
+        let pattern = components(path::Path::new(&pattern));
+        let path = components(&self.path);
+        matches_components(&pattern, &path)
+    }
+}
+
+fn expand_home(path: &str) -> String {
+    if path.starts_with('~') {
+        // TODO figure out these string lossy stuff
+        let home_str = dirs::home_dir().unwrap().to_string_lossy().to_string();
+        path.replacen("~", &home_str, 1)
+    } else {
+        path.to_string()
+    }
+}
+
+fn components(path: &path::Path) -> Vec<String> {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect()
+}
+
+fn matches_components(pattern: &[String], path: &[String]) -> bool {
+    match (pattern.split_first(), path.split_first()) {
+        (None, _) => path.is_empty(),
+        (Some((first, rest)), _) if first == "**" => {
+            (0..=path.len()).any(|skip| matches_components(rest, &path[skip..]))
+        }
+        (Some((first, rest)), Some((head, tail))) => {
+            (first == "*" || first == head) && matches_components(rest, tail)
+        }
+        (Some(_), None) => false,
+    }
+}
+
+/// To match the `cd` behavior, when the path '-' is passed try to
+/// go to the previous location based on $OLDPWD.
+/// If that env var is missing go home.
+fn patch_oldpwd(path: &str) -> String {
+    if path == "-" {
+        if let Ok(val) = std::env::var("OLDPWD") {
+            val
+        } else {
+            String::from("~")
+        }
+    } else {
+        path.to_string()
+    }
+}
+
+impl PartialEq for Location {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path
+    }
+}
+
+impl Eq for Location {}
+
+impl std::hash::Hash for Location {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.path.hash(state)
+    }
+}
+
+impl std::fmt::Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let home = dirs::home_dir().unwrap().to_string_lossy().to_string();
+        let mut loc = self.path.to_string_lossy().replace(&home, "~");
+        if loc == "~" {
+            loc = "home".to_string();
+        }
+        write!(f, "{}", loc)
+    }
+}
+
+/// Directory names that mark a location as swarming with enemies.
+const INFESTED_DIRS: [&str; 3] = ["node_modules", "target", "build"];
+
+/// Directories with at least this amount of entries are considered crowded.
+const CROWDED_FILE_COUNT: usize = 50;
+
+const LARGE_FILE_BYTES: u64 = 50 * 1024 * 1024;
+
+/// What's inside a directory, beyond its distance from home.
+#[derive(Default, Debug, Clone)]
+pub struct Features {
+    /// The entries in the directory, which are no longer counted once
+    /// all the other features are known and the directory is crowded.
+    pub file_count: usize,
+    /// The directory is a git repository.
+    pub is_repo: bool,
+    /// The directory is (or is within) a dependency or build artifacts dir.
+    pub is_infested: bool,
+    pub is_hidden: bool,
+    pub has_large_files: bool,
+}
+
+impl Features {
+    fn is_crowded(&self) -> bool {
+        self.file_count >= CROWDED_FILE_COUNT
+    }
+
+    /// Extra chances for enemies to appear.
+    pub fn enemy_bonus(&self) -> u32 {
+        self.is_crowded() as u32 + self.is_infested as u32
+    }
+
+    /// Extra chances to find gold and equipment.
+    pub fn treasure_bonus(&self) -> u32 {
+        self.has_large_files as u32
+    }
+}
+
+/// Some decisions are made branching on whether the distance from the home dir
+/// is small, medium or large. This enum encapsulate the definition of those.
+pub enum Distance {
+    Near(i32),
+    Mid(i32),
+    Far(i32),
+}
+
+impl Distance {
+    pub fn from(len: i32) -> Self {
+        match len {
+            n if n <= 4 => Self::Near(len),
+            n if n <= 9 => Self::Mid(len),
+            _ => Self::Far(len),
+        }
+    }
+
+    pub fn len(&self) -> i32 {
+        match self {
+            Distance::Near(s) => *s,
+            Distance::Mid(s) => *s,
+            Distance::Far(s) => *s,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from() {
+        assert_ne!(Location::from("/").unwrap(), Location::home());
+        assert_eq!(Location::from("~").unwrap(), Location::from("~/").unwrap());
+        assert_eq!(
+            Location::from("~/.").unwrap(),
+            Location::from("~/").unwrap()
+        );
+        // FIXME this only works if /usr/bin exists
+        // assert_eq!(
+        //     Location::from("/usr").unwrap(),
+        //     Location::from("/usr/bin/../").unwrap()
+        // );
+    }
+
+    #[test]
+    fn test_walk_towards() {
+        let source = location_from("/Users/facundo/dev/");
+        let dest = location_from("/");
+
+        let source = source.go_to(&dest);
+        assert_eq!(location_from("/Users/facundo/"), source);
+        let source = source.go_to(&dest);
+        assert_eq!(location_from("/Users/"), source);
+        let source = source.go_to(&dest);
+        assert_eq!(location_from("/"), source);
+        let source = source.go_to(&dest);
+        assert_eq!(location_from("/"), source);
+
+        let source = location_from("/Users/facundo/rust/rpg");
+        let dest = location_from("/Users/facundo/erlang/app");
+
+        let source = source.go_to(&dest);
+        assert_eq!(location_from("/Users/facundo/rust/"), source);
+        let source = source.go_to(&dest);
+        assert_eq!(location_from("/Users/facundo/"), source);
+        let source = source.go_to(&dest);
+        assert_eq!(location_from("/Users/facundo/erlang"), source);
+        let source = source.go_to(&dest);
+        assert_eq!(location_from("/Users/facundo/erlang/app"), source);
+    }
+
+    #[test]
+    fn test_distance() {
+        let distance = |from, to| location_from(from).distance_from(&location_from(to));
+
+        assert_eq!(distance("/Users/facundo", "/Users/facundo").len(), 0);
+        assert_eq!(distance("/Users/facundo", "/Users/facundo/other").len(), 1);
+        assert_eq!(distance("/Users/facundo/other", "/Users/facundo/").len(), 1);
+        assert_eq!(distance("/Users/facundo/other", "/").len(), 3);
+        assert_eq!(distance("/", "/Users/facundo/other").len(), 3);
+        assert_eq!(
+            distance("/Users/rusty/cage", "/Users/facundo/other").len(),
+            4
+        );
+        assert_eq!(
+            distance("/Users/facundo/other", "/Users/rusty/cage").len(),
+            4
+        );
+        assert_eq!(Location::home().distance_from_home().len(), 0);
+    }
+
+    #[test]
+    fn test_matches() {
+        assert!(location_from("/").matches("/"));
+        assert!(!location_from("/etc").matches("/"));
+        assert!(location_from("/etc").matches("/etc"));
+        assert!(location_from("/etc/").matches("/etc"));
+        assert!(!location_from("/etc/ssh").matches("/etc"));
+        assert!(location_from("/etc/ssh").matches("/etc/*"));
+        assert!(!location_from("/etc").matches("/etc/*"));
+
+        assert!(location_from("/project/node_modules").matches("/**/node_modules"));
+        assert!(location_from("/a/b/c/node_modules").matches("/**/node_modules"));
+        assert!(!location_from("/a/node_modules/c").matches("/**/node_modules"));
+        assert!(location_from("/a/node_modules/c").matches("/**/node_modules/**"));
+
+        let home = Location::home().path;
+        let location = Location::new(home.join("dev").join("app").join("node_modules"));
+        assert!(location.matches("~/**/node_modules"));
+        assert!(!location_from("/node_modules").matches("~/**/node_modules"));
+    }
+
+    #[test]
+    fn test_seed() {
+        let seed = location_from("/Users/facundo/dev").seed();
+        assert_eq!(seed, location_from("/Users/facundo/dev").seed());
+        assert_ne!(seed, location_from("/Users/facundo").seed());
+        assert_ne!(seed, location_from("/Users/facundo/dev/rpg").seed());
+    }
+
+    #[test]
+    fn test_features() {
+        let dir = std::env::temp_dir().join("rpg-features-test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join(".git")).unwrap();
+        for i in 0..CROWDED_FILE_COUNT {
+            fs::write(dir.join(format!("file{}", i)), "").unwrap();
+        }
+
+        let location = location_from(dir.to_str().unwrap());
+        let features = location.features();
+        assert!(features.is_repo);
+        assert!(!features.is_infested);
+        assert!(!features.is_hidden);
+        assert!(!features.has_large_files);
+        assert_eq!(CROWDED_FILE_COUNT + 1, features.file_count);
+        assert_eq!(1, features.enemy_bonus());
+        assert_eq!(0, features.treasure_bonus());
+
+        // the contents are only looked at once
+        fs::write(dir.join("other"), "").unwrap();
+        assert_eq!(CROWDED_FILE_COUNT + 1, location.features().file_count);
+        fs::remove_dir_all(&dir).unwrap();
+
+        // missing dirs only get the features derived from the path
+        let location = location_from("/missing/node_modules/.bin");
+        let features = location.features();
+        assert!(features.is_infested);
+        assert!(features.is_hidden);
+        assert!(!features.is_repo);
+        assert_eq!(0, features.file_count);
+        assert_eq!(1, features.enemy_bonus());
+    }
+
+    /// test-only equivalent for Location::from, specifically to bypass
+    /// path existence checks.
+    fn location_from(path: &str) -> Location {
+        let path = path::Path::new(path);
+        Location::new(path.to_path_buf())
+    }
+}
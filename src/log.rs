@@ -1,7 +1,11 @@
-use crate::character::{Character, StatusEffect};
+use crate::character::{ghost, Affinity, Character, StatusEffect};
+use crate::config::Config;
 use crate::event::Event;
 use crate::game::battle::AttackType;
+use crate::game::tombstone::Tombstone;
 use crate::game::Game;
+use crate::item::craft::Recipe;
+use crate::item::equipment::{Affix, DURABILITY};
 use crate::item::shop;
 use crate::location::Location;
 use colored::*;
@@ -36,19 +40,39 @@ pub fn handle(game: &Game, event: &Event) {
             kind,
             damage,
             mp_cost,
+            affinity,
         } => {
-            attack(enemy, kind, *damage, *mp_cost);
+            attack(enemy, kind, *damage, *mp_cost, *affinity);
         }
         Event::EnemyAttack {
             kind,
             damage,
             mp_cost,
+            affinity,
         } => {
-            attack(&game.player, kind, *damage, *mp_cost);
+            attack(&game.player, kind, *damage, *mp_cost, *affinity);
         }
         Event::StatusEffectDamage { damage } => {
             status_effect_damage(&game.player, *damage);
         }
+        Event::EnemyStatusEffectDamage { enemy, damage } => {
+            status_effect_damage(enemy, *damage);
+        }
+        Event::SkillUsed { name } => {
+            skill_used(&game.player, name);
+        }
+        Event::Steal { items } => {
+            steal(&game.player, items);
+        }
+        Event::StatusEffectHeal { recovered_hp } => {
+            status_effect_heal(&game.player, *recovered_hp);
+        }
+        Event::PlayerTurnSkipped => {
+            turn_skipped(&game.player);
+        }
+        Event::EnemyTurnSkipped { enemy } => {
+            turn_skipped(enemy);
+        }
         Event::BattleWon {
             xp,
             levels_up,
@@ -103,10 +127,47 @@ pub fn handle(game: &Game, event: &Event) {
                 *healed,
             );
         }
+        Event::ChestLocked { level } => {
+            locked_chest(*level);
+        }
+        Event::MimicDetected => {
+            battle_log(&game.player, "mimic spotted!");
+        }
+        Event::TrapDisarmed => {
+            battle_log(&game.player, "trap disarmed!");
+        }
+        Event::TrapTriggered { damage, status } => {
+            trap_triggered(&game.player, *damage, *status);
+        }
         Event::ClassChanged { lost_xp } => change_class(&game.player, &game.location, *lost_xp),
         Event::LevelUp { .. } => {}
         Event::ItemBought { .. } => {}
         Event::ItemUsed { .. } => {}
+        Event::Buff { item, status } => {
+            let suffix = format!("{} {}", format_status_effect(*status), item);
+            battle_log(&game.player, &suffix);
+        }
+        Event::ItemSold { item, gold } => {
+            item_sold(item, *gold);
+        }
+        Event::EquipmentBroken { item } => {
+            battle_log(
+                &game.player,
+                &format!("{} broke!", item).bright_red().to_string(),
+            );
+        }
+        Event::ItemUpgraded {
+            item,
+            cost,
+            success,
+        } => {
+            item_upgraded(item, *cost, *success);
+        }
+        Event::ItemCrafted { recipe } => {
+            if !quiet() {
+                println!("    +{}", recipe);
+            }
+        }
         Event::GameReset => {}
     }
 }
@@ -122,15 +183,51 @@ pub fn status(game: &Game) {
     }
 }
 
-pub fn shop_list(game: &Game, items: Vec<Box<dyn shop::Shoppable>>) {
-    for item in items {
-        let display = format!("{}", item);
-        println!("    {:<10}  {}", display, format_gold(item.cost()));
+pub fn shop_list(game: &Game, offers: Vec<shop::Offer>) {
+    for offer in offers {
+        let display = format!("{}", offer.item);
+        println!(
+            "    {:<10}  {:<6} {}",
+            display,
+            format_gold(offer.item.cost()),
+            format!("x{}", offer.stock).dimmed()
+        );
     }
 
     println!("\n    funds: {}", format_gold(game.gold));
 }
 
+/// Print the recipes, dimming the ones missing materials.
+pub fn recipe_list(game: &Game, recipes: &[Recipe]) {
+    for recipe in recipes {
+        let mut materials = recipe
+            .materials
+            .iter()
+            .map(|(name, units)| format!("{}x{}", name, units))
+            .collect::<Vec<String>>();
+        materials.sort();
+
+        let line = format!("    {:<14}  {}", recipe.name, materials.join(","));
+        if recipe.is_available(game) {
+            println!("{}", line);
+        } else {
+            println!("{}", line.dimmed());
+        }
+    }
+
+    println!("\n    {}", format_materials(game));
+}
+
+pub fn purchase(game: &Game, spent: i32) {
+    if !quiet() {
+        println!(
+            "    spent: {}  funds: {}",
+            format_gold(spent),
+            format_gold(game.gold)
+        );
+    }
+}
+
 pub fn quest_list(quests: Vec<(bool, String)>) {
     for (completed, quest) in quests {
         if completed {
@@ -141,6 +238,51 @@ pub fn quest_list(quests: Vec<(bool, String)>) {
     }
 }
 
+pub fn graveyard(tombstones: Vec<(&String, &Tombstone)>) {
+    for (location, tombstone) in tombstones {
+        let (items, gold) = tombstone.contents();
+        println!(
+            "  {} {}[{}] {} {} {}{}",
+            location,
+            tombstone.class,
+            tombstone.level,
+            tombstone.owner.dimmed(),
+            format_age(tombstone.timestamp).dimmed(),
+            format_ls("\u{1FAA6} ", &items, gold),
+            format!("{}%", tombstone.remaining()).dimmed()
+        );
+    }
+}
+
+pub fn ghosts(ghosts: Vec<(&str, &Character, Option<&ghost::Record>)>) {
+    for (owner, hero, record) in ghosts {
+        let (wins, losses) = record.map_or((0, 0), |record| (record.wins, record.losses));
+        println!(
+            "  {} {}[{}] {} {}",
+            owner,
+            hero.name(),
+            hero.level,
+            format_equipment(hero),
+            format!("won:{} lost:{}", wins, losses).dimmed()
+        );
+    }
+}
+
+fn item_sold(item: &str, gold: i32) {
+    if !quiet() {
+        println!("    {} {}", format_gold_plus(gold), item);
+    }
+}
+
+fn item_upgraded(item: &str, cost: i32, success: bool) {
+    let cost = format!("-{}g", cost).yellow();
+    if success {
+        println!("    {} {}", cost, item);
+    } else {
+        println!("    {} {}", cost, "upgrade failed!".bright_red());
+    }
+}
+
 pub fn quest_done(reward: i32) {
     if !quiet() {
         println!("    {} quest completed!", format_gold_plus(reward));
@@ -221,6 +363,20 @@ fn heal_item(player: &Character, item: &str, recovered_hp: i32, recovered_mp: i3
     }
 }
 
+fn skill_used(player: &Character, name: &str) {
+    if !quiet() {
+        battle_log(player, &format!("{}!", name).cyan().to_string());
+    }
+}
+
+fn steal(player: &Character, items: &[String]) {
+    if items.is_empty() {
+        battle_log(player, "can't steal!");
+    } else {
+        battle_log(player, &format!("stole{}", format_ls("", items, 0)));
+    }
+}
+
 fn change_class(player: &Character, location: &Location, lost_xp: i32) {
     let mut lost_text = String::new();
     if lost_xp > 0 {
@@ -229,11 +385,17 @@ fn change_class(player: &Character, location: &Location, lost_xp: i32) {
     log(player, location, &lost_text);
 }
 
-fn attack(character: &Character, attack: &AttackType, damage: i32, mp_cost: i32) {
+fn attack(
+    character: &Character,
+    attack: &AttackType,
+    damage: i32,
+    mp_cost: i32,
+    affinity: Affinity,
+) {
     if !quiet() {
         battle_log(
             character,
-            &format_attack(character, &attack, damage, mp_cost),
+            &format_attack(character, &attack, damage, mp_cost, affinity),
         );
     }
 }
@@ -243,6 +405,30 @@ fn status_effect_damage(character: &Character, damage: i32) {
     battle_log(character, &format_damage(character, damage, &emoji));
 }
 
+fn status_effect_heal(character: &Character, recovered_hp: i32) {
+    let (_, emoji) = status_effect_params(character.status_effect.unwrap());
+    battle_log(
+        character,
+        &format!("+{}hp {}", recovered_hp, emoji).green().to_string(),
+    );
+}
+
+fn trap_triggered(player: &Character, damage: i32, status: Option<StatusEffect>) {
+    let suffix = if let Some(status) = status {
+        format!("trap! {}", format_status_effect(status))
+    } else {
+        format_damage(player, damage, "trap!")
+    };
+    battle_log(player, &suffix);
+}
+
+fn turn_skipped(character: &Character) {
+    if !quiet() {
+        let (name, emoji) = status_effect_params(character.status_effect.unwrap());
+        battle_log(character, &format!("{} {}", emoji, name));
+    }
+}
+
 fn battle_lost(player: &Character) {
     battle_log(player, "\u{1F480}");
 }
@@ -271,16 +457,16 @@ fn long_status(game: &Game) {
     let player = &game.player;
     let location = &game.location;
 
-    println!("{}@{}", format_character(player), location);
+    println!("{}@{}", format_character(player), format_location(location));
     println!(
         "    hp:{} {}/{}",
         hp_display(player, 10),
         player.current_hp,
-        player.max_hp
+        player.effective_max_hp()
     );
 
     let (current_mp, max_mp) = if player.class.is_magic() {
-        (player.current_mp, player.max_mp)
+        (player.current_mp, player.effective_max_mp())
     } else {
         (0, 0)
     };
@@ -298,15 +484,39 @@ fn long_status(game: &Game) {
         player.xp_for_next()
     );
     if let Some(status) = player.status_effect {
-        println!("    status: {}", format_status_effect(status).bright_red());
+        let status_text = if status.is_positive() {
+            format_status_effect(status).green()
+        } else {
+            format_status_effect(status).bright_red()
+        };
+        println!("    status: {}", status_text);
     }
     println!(
         "    att:{}   mag:{}   def:{}   spd:{}",
         player.physical_attack(),
         player.magic_attack(),
         player.deffense(),
-        player.speed
+        player.effective_speed()
     );
+    let skills = player
+        .skills()
+        .iter()
+        .map(|skill| skill.name.clone())
+        .collect::<Vec<String>>();
+    if !skills.is_empty() {
+        println!("    skills: {}", skills.join(", "));
+    }
+    let bonuses = Affix::ALL
+        .iter()
+        .filter_map(|affix| {
+            let bonus = player.affix_bonus(*affix);
+            let unit = if affix.is_percentage() { "%" } else { "" };
+            (bonus > 0).then(|| format!("{}+{}{}", affix, bonus, unit))
+        })
+        .collect::<Vec<String>>();
+    if !bonuses.is_empty() {
+        println!("    bonus: {}", bonuses.join(" "));
+    }
     println!("    {}", format_equipment(player));
     println!("    {}", format_inventory(game));
     println!("    {}", format_gold(game.gold));
@@ -340,15 +550,15 @@ fn plain_status(game: &Game) {
         player.level,
         game.location,
         player.current_hp,
-        player.max_hp,
+        player.effective_max_hp(),
         player.current_mp,
-        player.max_mp,
+        player.effective_max_mp(),
         player.xp,
         player.xp_for_next(),
         player.magic_attack(),
         player.physical_attack(),
         player.deffense(),
-        player.speed,
+        player.effective_speed(),
         status_effect,
         format_equipment(player),
         format_inventory(game),
@@ -364,6 +574,24 @@ fn tombstone(items: &[String], gold: i32) {
     println!("{}", format_ls("\u{1FAA6} ", items, gold));
 }
 
+fn locked_chest(level: i32) {
+    println!("\u{1F512} key[{}] needed", level);
+}
+
+/// Describe how long ago the given unix timestamp was, e.g. `3h ago`.
+fn format_age(timestamp: u64) -> String {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let hours = now.saturating_sub(timestamp) / 3600;
+    if hours < 24 {
+        format!("{}h ago", hours)
+    } else {
+        format!("{}d ago", hours / 24)
+    }
+}
+
 fn format_ls(emoji: &str, items: &[String], gold: i32) -> String {
     let mut string = format!("{} ", emoji);
 
@@ -387,11 +615,21 @@ fn log(character: &Character, location: &Location, suffix: &str) {
         hp_display(character, 4),
         mp_display(character, 4),
         xp_display(character, 4),
-        location,
+        format_location(location),
         suffix
     );
 }
 
+fn format_location(location: &Location) -> String {
+    if location.is_town() {
+        format!("{} {}", location, "(town)".dimmed())
+    } else if let Some(biome) = location.biome() {
+        format!("{} {}", location, format!("({})", biome.name).dimmed())
+    } else {
+        location.to_string()
+    }
+}
+
 fn battle_log(character: &Character, suffix: &str) {
     println!(
         "{}{} {}",
@@ -403,7 +641,7 @@ fn battle_log(character: &Character, suffix: &str) {
 
 fn format_character(character: &Character) -> String {
     let name = format!("{:>8}", character.name());
-    let name = if character.name() == "shadow" {
+    let name = if character.name() == "shadow" || ghost::owner(character).is_some() {
         name.dimmed()
     } else if character.is_player() {
         name.bold()
@@ -414,15 +652,11 @@ fn format_character(character: &Character) -> String {
 }
 
 fn format_equipment(character: &Character) -> String {
-    let mut fragments = Vec::new();
-
-    if let Some(sword) = &character.sword {
-        fragments.push(sword.to_string());
-    }
-
-    if let Some(shield) = &character.shield {
-        fragments.push(shield.to_string());
-    }
+    let fragments: Vec<String> = character
+        .equipment()
+        .iter()
+        .map(|equip| equip.to_string())
+        .collect();
     format!("equip:{{{}}}", fragments.join(","))
 }
 
@@ -437,17 +671,89 @@ pub fn format_inventory(game: &Game) -> String {
     format!("item:{{{}}}", items.join(","))
 }
 
-fn format_attack(receiver: &Character, attack: &AttackType, damage: i32, mp_cost: i32) -> String {
+pub fn format_stash(game: &Game) -> String {
+    let stash = game
+        .stash
+        .iter()
+        .map(|gear| gear.to_string())
+        .collect::<Vec<String>>();
+    format!("stash:{{{}}}", stash.join(","))
+}
+
+pub fn format_materials(game: &Game) -> String {
+    let mut materials = game
+        .materials
+        .iter()
+        .map(|(k, v)| format!("{}x{}", k, v))
+        .collect::<Vec<String>>();
+
+    materials.sort();
+    format!("materials:{{{}}}", materials.join(","))
+}
+
+/// The uses left of the equipment that wears out, as a percentage.
+fn format_durability(player: &Character) -> String {
+    let durability = player
+        .equipment()
+        .iter()
+        .filter_map(|equipment| {
+            let left = equipment.durability()? * 100 / DURABILITY;
+            let text = format!("{}:{}%", equipment, left);
+            Some(if equipment.is_worn() {
+                text.bright_red().to_string()
+            } else {
+                text
+            })
+        })
+        .collect::<Vec<String>>();
+    format!("durability:{{{}}}", durability.join(","))
+}
+
+/// Print the equipment in use, the stash, the items and the materials of the hero.
+pub fn inventory(game: &Game) {
+    println!("    {}", format_equipment(&game.player));
+    if Config::get().durability {
+        println!("    {}", format_durability(&game.player));
+    }
+    println!("    {}", format_stash(game));
+    println!("    {}", format_inventory(game));
+    println!("    {}", format_materials(game));
+}
+
+fn format_attack(
+    receiver: &Character,
+    attack: &AttackType,
+    damage: i32,
+    mp_cost: i32,
+    affinity: Affinity,
+) -> String {
     let magic_effect = if mp_cost > 0 { "\u{2728}" } else { "" };
+    let affinity_effect = match affinity {
+        Affinity::Weak => " super effective!",
+        Affinity::Resistant => " resisted",
+        Affinity::Neutral => "",
+    };
 
     match attack {
-        AttackType::Regular => format_damage(receiver, damage, &magic_effect),
-        AttackType::Critical => {
-            format_damage(receiver, damage, &format!("{} critical!", magic_effect))
-        }
-        AttackType::Effect(status_effect) => {
-            format_damage(receiver, damage, &format_status_effect(*status_effect))
-        }
+        AttackType::Regular => format_damage(
+            receiver,
+            damage,
+            format!("{}{}", magic_effect, affinity_effect).trim_start(),
+        ),
+        AttackType::Critical => format_damage(
+            receiver,
+            damage,
+            &format!("{} critical!{}", magic_effect, affinity_effect),
+        ),
+        AttackType::Effect(status_effect) => format_damage(
+            receiver,
+            damage,
+            &format!(
+                "{}{}",
+                format_status_effect(*status_effect),
+                affinity_effect
+            ),
+        ),
         AttackType::Miss => format!("{} dodged!", magic_effect),
     }
 }
@@ -470,6 +776,11 @@ fn status_effect_params(status_effect: StatusEffect) -> (&'static str, &'static
     match status_effect {
         StatusEffect::Burn => ("burn", "\u{1F525}"),
         StatusEffect::Poison => ("poison", "\u{2620}\u{FE0F} "),
+        StatusEffect::Sleep => ("sleep", "\u{1F4A4}"),
+        StatusEffect::Stun => ("stun", "\u{1F4AB}"),
+        StatusEffect::Weakness => ("weakness", "\u{1F940}"),
+        StatusEffect::Regen => ("regen", "\u{1F49A}"),
+        StatusEffect::Haste => ("haste", "\u{26A1}"),
     }
 }
 
@@ -477,7 +788,7 @@ fn hp_display(character: &Character, slots: i32) -> String {
     bar_display(
         slots,
         character.current_hp,
-        character.max_hp,
+        character.effective_max_hp(),
         "green",
         "red",
     )
@@ -493,7 +804,7 @@ fn mp_display(character: &Character, slots: i32) -> String {
     bar_display(
         slots,
         current_mp,
-        character.max_mp,
+        character.effective_max_mp(),
         "purple",
         "bright black",
     )
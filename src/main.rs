@@ -2,6 +2,7 @@ use game::Game;
 
 mod character;
 mod command;
+mod config;
 mod datafile;
 mod event;
 mod game;
@@ -41,7 +42,11 @@ fn main() {
         datafile::remove();
     }
 
+    datafile::load_config();
     datafile::load_classes();
+    datafile::load_bosses();
+    datafile::load_biomes();
+    datafile::load_recipes();
 
     let mut game = datafile::load().unwrap_or_else(|_| Game::new());
 
@@ -38,6 +38,17 @@ pub fn dev() -> Box<dyn Quest> {
     })
 }
 
+pub fn boss(name: &str, location: &str) -> Box<dyn Quest> {
+    let mut to_beat = HashSet::new();
+    to_beat.insert(name.to_string());
+
+    Box::new(BeatEnemyClass {
+        to_beat,
+        total: 1,
+        description: format!("beat the {} at {}", name, location),
+    })
+}
+
 pub fn at_distance(distance: i32) -> Box<dyn Quest> {
     Box::new(BeatEnemyDistance { distance })
 }
@@ -56,6 +67,10 @@ impl Quest for BeatEnemyClass {
         format!("{} {}/{}", self.description, already_beat, self.total)
     }
 
+    fn title(&self) -> String {
+        self.description.clone()
+    }
+
     fn handle(&mut self, event: &Event) -> bool {
         if let Event::BattleWon { enemy, .. } = event {
             self.to_beat.remove(&enemy.name());
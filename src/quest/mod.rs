@@ -1,3 +1,4 @@
+use crate::character::boss::Boss;
 use crate::character::class;
 use crate::event;
 use crate::game;
@@ -50,6 +51,8 @@ impl QuestList {
 
         self.quests
             .push((Status::Locked(2), 200, Box::new(tutorial::FindChest)));
+        self.quests
+            .push((Status::Locked(2), 100, Box::new(tutorial::SellItem)));
         self.quests
             .push((Status::Locked(2), 500, Box::new(level::ReachLevel::new(5))));
         self.quests.push((
@@ -60,6 +63,11 @@ impl QuestList {
 
         self.quests
             .push((Status::Locked(5), 200, Box::new(tutorial::VisitTomb)));
+        self.quests.push((
+            Status::Locked(5),
+            1000,
+            Box::new(tutorial::DisarmTraps::new(5)),
+        ));
         self.quests.push((
             Status::Locked(5),
             1000,
@@ -98,6 +106,8 @@ impl QuestList {
         self.quests
             .push((Status::Locked(15), 20000, beat_enemy::dev()));
 
+        self.add_boss_quests(1);
+
         self.quests.push((
             Status::Locked(50),
             100000,
@@ -105,6 +115,29 @@ impl QuestList {
         ));
     }
 
+    /// Add the quests to beat the bosses that don't have one yet, e.g. in
+    /// games saved before they were introduced or configured, unlocking
+    /// those for bosses within reach of a hero of the given level.
+    pub fn add_boss_quests(&mut self, level: i32) {
+        for boss in Boss::all() {
+            let quest = beat_enemy::boss(boss.name(), &boss.location);
+            if self
+                .quests
+                .iter()
+                .any(|(_, _, q)| q.title() == quest.title())
+            {
+                continue;
+            }
+
+            let status = if boss.level / 2 < level {
+                Status::Unlocked
+            } else {
+                Status::Locked(boss.level / 2)
+            };
+            self.quests.push((status, boss.level * 1000, quest));
+        }
+    }
+
     /// Pass the event to each of the quests, moving the completed ones to DONE.
     /// The total gold reward is returned.
     fn handle(&mut self, event: &event::Event) -> i32 {
@@ -165,6 +198,11 @@ pub trait Quest {
     /// Update the quest progress based on the given event and
     /// return whether the quest was finished.
     fn handle(&mut self, event: &event::Event) -> bool;
+
+    /// The description without the quest progress, to tell quests apart.
+    fn title(&self) -> String {
+        self.description()
+    }
 }
 
 impl fmt::Display for dyn Quest {
@@ -180,7 +218,7 @@ mod tests {
 
     #[test]
     fn test_quest_status() {
-        let mut quests = QuestList { quests: Vec::new() };
+        let mut quests = QuestList::default();
         quests
             .quests
             .push((Status::Unlocked, 10, Box::new(level::ReachLevel::new(2))));
@@ -271,6 +309,31 @@ mod tests {
         assert_eq!(1, count_status(&game.quests, Status::Completed));
     }
 
+    #[test]
+    fn test_add_boss_quests() {
+        // games saved before bosses were introduced
+        let mut quests: QuestList = serde_json::from_str(r#"{"quests":[]}"#).unwrap();
+        quests.add_boss_quests(10);
+        assert_eq!(Boss::all().len(), quests.quests.len());
+        let unlocked = Boss::all().iter().filter(|boss| boss.level / 2 < 10);
+        assert_eq!(unlocked.count(), count_status(&quests, Status::Unlocked));
+
+        // they are only added once, even after making progress
+        let boss = &Boss::all()[0];
+        quests.handle(&event::Event::BattleWon {
+            enemy: &boss.character(),
+            location: game::Game::new().location,
+            xp: 100,
+            levels_up: 0,
+            gold: 100,
+            player_class: "warrior".to_string(),
+            items: &[],
+        });
+        assert_eq!(1, count_status(&quests, Status::Completed));
+        quests.add_boss_quests(10);
+        assert_eq!(Boss::all().len(), quests.quests.len());
+    }
+
     fn count_status(quests: &QuestList, status: Status) -> usize {
         quests
             .quests
@@ -35,6 +35,20 @@ impl Quest for BuySword {
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SellItem;
+
+#[typetag::serde]
+impl Quest for SellItem {
+    fn description(&self) -> String {
+        "sell an item".to_string()
+    }
+
+    fn handle(&mut self, event: &Event) -> bool {
+        matches!(event, Event::ItemSold { .. })
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct UsePotion;
 
@@ -93,3 +107,33 @@ impl Quest for VisitTomb {
         )
     }
 }
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DisarmTraps {
+    remaining: i32,
+    total: i32,
+}
+
+impl DisarmTraps {
+    pub fn new(total: i32) -> Self {
+        Self {
+            remaining: total,
+            total,
+        }
+    }
+}
+
+#[typetag::serde]
+impl Quest for DisarmTraps {
+    fn description(&self) -> String {
+        let disarmed = self.total - self.remaining;
+        format!("disarm chest traps {}/{}", disarmed, self.total)
+    }
+
+    fn handle(&mut self, event: &Event) -> bool {
+        if let Event::TrapDisarmed = event {
+            self.remaining -= 1;
+        }
+        self.remaining <= 0
+    }
+}